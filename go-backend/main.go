@@ -5,81 +5,209 @@ import (
 	"bytes"
 	"crypto/tls"
 	"encoding/json"
+	"errors"
 	"fmt"
+	"io"
 	"io/ioutil"
+	"net"
 	"net/http"
 	"net/url"
 	"os"
-	"strconv"
+	"strings"
+	"syscall"
 	"time"
 )
 
 const loginURL = "https://controller.access.network/portal_api.php"
 
+// Version of the stdin/stdout protocol; must match PROTOCOL_VERSION in src/protocol.rs
+const protocolVersion = 1
+
+// Error codes understood by the Rust side (see ErrorCode in src/protocol.rs)
+const (
+	codeInvalidRequest     = "invalid_request"
+	codeNetwork            = "network"
+	codeDNS                = "dns"
+	codeConnectionRefused  = "connection_refused"
+	codeTimeout            = "timeout"
+	codeTLS                = "tls"
+	codeHTTPStatus         = "http_status"
+	codeInvalidCredentials = "invalid_credentials"
+	codeMalformedResponse  = "malformed_response"
+	codeSessionExpired     = "session_expired"
+)
+
+type Request struct {
+	Version int    `json:"version"`
+	ID      uint64 `json:"id"`
+	Action  string `json:"action"`
+	Params  struct {
+		Username string `json:"username"`
+		Password string `json:"password"`
+		Digest   string `json:"digest"`
+	} `json:"params"`
+}
+
+type ResponseError struct {
+	Code       string `json:"code"`
+	Message    string `json:"message"`
+	HTTPStatus int    `json:"http_status,omitempty"`
+}
+
+func (e *ResponseError) Error() string {
+	return e.Message
+}
+
+type Payload struct {
+	Username string `json:"username,omitempty"`
+	Digest   string `json:"digest,omitempty"`
+}
+
+type Response struct {
+	Version int            `json:"version"`
+	ID      uint64         `json:"id"`
+	Status  string         `json:"status"`
+	Error   *ResponseError `json:"error,omitempty"`
+	Payload *Payload       `json:"payload,omitempty"`
+}
+
 func main() {
-	// entry: [n]
-	// [arg1] (aka the reqType)
-	// [arg2]
-	// ...
-	// [argn]
+	// entry: a single JSON request on one line, e.g.
+	// {"version":1,"id":1,"action":"login","params":{"username":"...","password":"..."}}
+	// exit: a single JSON response on one line on stdout; logs go to stderr
+	//
+	// With --worker, the process keeps reading requests (one per line) until stdin is closed,
+	// answering each of them in order and reusing the same HTTP connection to the controller.
+	//
+	// With --version, the process answers with an empty successful response and exits, so the
+	// frontend can check at startup that it speaks the same protocol version.
+	if len(os.Args) > 1 && os.Args[1] == "--version" {
+		writeResponse(Response{Status: "ok"})
+		return
+	}
+	worker := len(os.Args) > 1 && os.Args[1] == "--worker"
+
 	scanner := bufio.NewScanner(os.Stdin)
-	var n int
-	var err error
-	scanner.Scan()
-	n, err = strconv.Atoi(scanner.Text())
-	if err != nil {
-		fmt.Println("Couldn't find n")
-		os.Exit(1)
+	client := NewClient()
+
+	if worker {
+		for scanner.Scan() {
+			line := scanner.Bytes()
+			if len(bytes.TrimSpace(line)) == 0 {
+				continue
+			}
+			writeResponse(handleRequest(client, line))
+		}
 		return
 	}
 
-	if n < 1 {
-		fmt.Println("n too small")
+	if !scanner.Scan() {
+		writeResponse(Response{Status: "error", Error: &ResponseError{Code: codeInvalidRequest, Message: "empty request"}})
 		os.Exit(1)
 		return
 	}
 
-	args := make([]string, n)
-	for i := range args {
-		scanner.Scan()
-		args[i] = scanner.Text()
+	response := handleRequest(client, scanner.Bytes())
+	writeResponse(response)
+	if response.Status != "ok" {
+		os.Exit(1)
+		return
 	}
+}
 
-	err = nil
+func handleRequest(client *Client, line []byte) Response {
+	var req Request
+	if err := json.Unmarshal(line, &req); err != nil {
+		return errorResponse(req, &ResponseError{Code: codeInvalidRequest, Message: "invalid JSON request: " + err.Error()})
+	}
+	if req.Version != protocolVersion {
+		return errorResponse(req, &ResponseError{Code: codeInvalidRequest, Message: fmt.Sprintf("unsupported protocol version %d, want %d", req.Version, protocolVersion)})
+	}
 
-	reqType := args[0]
-	switch reqType {
+	switch req.Action {
 	case "login":
-		if n != 3 {
-			fmt.Println("n too small: want 2 arguments, the username and the password")
-			os.Exit(1)
-			return
+		if req.Params.Username == "" || req.Params.Password == "" {
+			return errorResponse(req, &ResponseError{Code: codeInvalidRequest, Message: "login wants 2 params, the username and the password"})
+		}
+		username, digest, err := client.Login(req.Params.Username, req.Params.Password)
+		if err != nil {
+			return errorResponse(req, err)
 		}
-		client := Client{}
-		err = client.Login(args[1], args[2])
-		break
+		return okResponse(req, &Payload{Username: username, Digest: digest})
 	case "logout", "ping":
-		if n != 3 {
-			fmt.Println("n too small: want 2 arguments, the username and digest")
-			os.Exit(1)
-			return
+		if req.Params.Username == "" || req.Params.Digest == "" {
+			return errorResponse(req, &ResponseError{Code: codeInvalidRequest, Message: req.Action + " wants 2 params, the username and digest"})
 		}
-		client := Client{}
-		if reqType == "logout" {
-			err = client.Logout(args[1], args[2])
-		} else if reqType == "ping" {
-			err = client.Ping(args[1], args[2])
+		var err *ResponseError
+		if req.Action == "logout" {
+			err = client.Logout(req.Params.Username, req.Params.Digest)
+		} else {
+			err = client.Ping(req.Params.Username, req.Params.Digest)
 		}
-		break
+		if err != nil {
+			return errorResponse(req, err)
+		}
+		return okResponse(req, nil)
 	default:
-		fmt.Println("invalid request type")
-		os.Exit(1)
-		return
+		return errorResponse(req, &ResponseError{Code: codeInvalidRequest, Message: "invalid request type: " + req.Action})
 	}
+}
+
+func okResponse(req Request, payload *Payload) Response {
+	return Response{Version: protocolVersion, ID: req.ID, Status: "ok", Payload: payload}
+}
+
+func errorResponse(req Request, err *ResponseError) Response {
+	return Response{Version: protocolVersion, ID: req.ID, Status: "error", Error: err}
+}
+
+func writeResponse(response Response) {
+	response.Version = protocolVersion
+	data, err := json.Marshal(response)
 	if err != nil {
-		os.Exit(1)
+		fmt.Fprintln(os.Stderr, "Error marshalling response:", err)
 		return
 	}
+	os.Stdout.Write(append(data, '\n'))
+}
+
+// classifyError turns a transport error into a protocol error code
+func classifyError(err error) *ResponseError {
+	inner := err
+	if urlErr, ok := inner.(*url.Error); ok {
+		inner = urlErr.Err
+	}
+	if opErr, ok := inner.(*net.OpError); ok {
+		inner = opErr.Err
+	}
+
+	code := codeNetwork
+	var netErr net.Error
+	switch inner.(type) {
+	case *net.DNSError:
+		code = codeDNS
+	case tls.RecordHeaderError:
+		code = codeTLS
+	default:
+		if strings.HasPrefix(inner.Error(), "tls: ") {
+			code = codeTLS
+		} else if errors.Is(err, syscall.ECONNREFUSED) {
+			code = codeConnectionRefused
+		} else if errors.As(err, &netErr) && netErr.Timeout() {
+			code = codeTimeout
+		}
+	}
+	return &ResponseError{Code: code, Message: err.Error()}
+}
+
+func httpStatusError(prefix string, status int, body []byte) *ResponseError {
+	fmt.Fprintln(os.Stderr, prefix, "Error response status code:", status)
+	fmt.Fprintln(os.Stderr, prefix, "Response body:", string(body))
+	return &ResponseError{
+		Code:       codeHTTPStatus,
+		Message:    fmt.Sprintf("%s Error response status code: %d", prefix, status),
+		HTTPStatus: status,
+	}
 }
 
 // =================================================================================================
@@ -180,9 +308,20 @@ type LoginResponse struct {
 }
 
 type Client struct {
+	http *http.Client
+	url  string
 }
 
-func (c *Client) newUnsecureHTTPClient() *http.Client {
+// NewClient uses the controller address given by the frontend in PORTAL_URL, if any
+func NewClient() *Client {
+	url := loginURL
+	if env := os.Getenv("PORTAL_URL"); env != "" {
+		url = env
+	}
+	return &Client{http: newUnsecureHTTPClient(), url: url}
+}
+
+func newUnsecureHTTPClient() *http.Client {
 	tlsConfig := &tls.Config{
 		MinVersion:         tls.VersionTLS10,
 		MaxVersion:         tls.VersionTLS10,
@@ -192,113 +331,135 @@ func (c *Client) newUnsecureHTTPClient() *http.Client {
 	client := &http.Client{
 		Transport: &http.Transport{
 			TLSClientConfig: tlsConfig,
+			// the connection is kept open between requests in worker mode
+			IdleConnTimeout: 90 * time.Second,
 		},
+		Timeout: 15 * time.Second,
 	}
 	return client
 }
 
-func (c *Client) Login(username string, password string) error {
-	client := c.newUnsecureHTTPClient()
+// post sends a form to the controller; a request on a kept-alive connection that the controller
+// already closed fails immediately, so it is retried once on a fresh connection. Other errors are
+// not retried: a timeout would otherwise take twice the client timeout, past the frontend deadline
+// (BACKEND_TIMEOUT in src/backend.rs)
+func (c *Client) post(body []byte) (*http.Response, error) {
+	var resp *http.Response
+	var err error
+	start := time.Now()
+	for attempt := 0; attempt < 2; attempt++ {
+		var r *http.Request
+		r, err = http.NewRequest("POST", c.url, bytes.NewReader(body))
+		if err != nil {
+			return nil, err
+		}
+		r.Header.Add("Content-Type", "application/x-www-form-urlencoded")
+
+		resp, err = c.http.Do(r)
+		if err == nil {
+			return resp, nil
+		}
+		if !isStaleConnection(err) || time.Since(start) > staleRetryWindow {
+			break
+		}
+	}
+	return nil, err
+}
+
+// A closed kept-alive connection is noticed at once; a later failure is a real one
+const staleRetryWindow = 2 * time.Second
 
-	body := []byte(fmt.Sprintf(`action=authenticate&login=%s&password=%s&policy_accept=false`, url.QueryEscape(username), url.QueryEscape(password)))
+// isStaleConnection reports a kept-alive connection closed by the controller between two requests
+func isStaleConnection(err error) bool {
+	return errors.Is(err, io.EOF) ||
+		errors.Is(err, io.ErrUnexpectedEOF) ||
+		errors.Is(err, syscall.ECONNRESET) ||
+		errors.Is(err, syscall.EPIPE)
+}
 
-	r, err := http.NewRequest("POST", loginURL, bytes.NewBuffer(body))
-	r.Header.Add("Content-Type", "application/x-www-form-urlencoded")
+func (c *Client) Login(username string, password string) (string, string, *ResponseError) {
+	body := []byte(fmt.Sprintf(`action=authenticate&login=%s&password=%s&policy_accept=false`, url.QueryEscape(username), url.QueryEscape(password)))
 
-	resp, err := client.Do(r)
+	resp, err := c.post(body)
 	if err != nil {
-		fmt.Println("Error making request:", err)
-		return err
+		fmt.Fprintln(os.Stderr, "Error making request:", err)
+		return "", "", classifyError(err)
 	}
 	defer resp.Body.Close()
 
 	bodyBytes, err := ioutil.ReadAll(resp.Body)
 	if err != nil {
-		fmt.Println("Error reading body:", err)
-		return err
+		fmt.Fprintln(os.Stderr, "Error reading body:", err)
+		return "", "", classifyError(err)
 	}
 
 	if resp.StatusCode != 200 {
-		fmt.Println("[login] Error response status code:", resp.StatusCode)
-		fmt.Println("[login] Response body:", string(bodyBytes))
-		return fmt.Errorf("[login] Error response status code: %d", resp.StatusCode)
+		return "", "", httpStatusError("[login]", resp.StatusCode, bodyBytes)
 	}
 
 	var loginResponse LoginResponse
 	err = json.Unmarshal(bodyBytes, &loginResponse)
 	if err != nil {
-		fmt.Println("Error unmarshalling response:", err)
-		return err
+		fmt.Fprintln(os.Stderr, "Error unmarshalling response:", err)
+		return "", "", &ResponseError{Code: codeMalformedResponse, Message: "Error unmarshalling response: " + err.Error()}
 	}
 	// check that the stuff is actually defined till the password digest
 	username = loginResponse.User.Login.Value
 	passwordDigest := loginResponse.User.PasswordDigest.Value
 
 	if username == "" || passwordDigest == "" {
-		fmt.Println("Identifiants incorrects")
-		return fmt.Errorf("Identifiants incorrects")
+		return "", "", &ResponseError{Code: codeInvalidCredentials, Message: "Identifiants incorrects"}
 	}
 
-	fmt.Println(username)
-	fmt.Println(passwordDigest)
-	return nil
+	return username, passwordDigest, nil
 }
 
-func (c *Client) Ping(username string, passwordDigest string) error {
-	client := c.newUnsecureHTTPClient()
-
+func (c *Client) Ping(username string, passwordDigest string) *ResponseError {
 	body := []byte(fmt.Sprintf(`action=refresh&login=%s&password_digest=%s&policy_accept=false`, url.QueryEscape(username), url.QueryEscape(passwordDigest)))
 
-	r, err := http.NewRequest("POST", loginURL, bytes.NewBuffer(body))
-	r.Header.Add("Content-Type", "application/x-www-form-urlencoded")
-
-	resp, err := client.Do(r)
+	resp, err := c.post(body)
 	if err != nil {
-		fmt.Println("Error making request:", err)
-		return err
+		fmt.Fprintln(os.Stderr, "Error making request:", err)
+		return classifyError(err)
 	}
 	defer resp.Body.Close()
 
 	if resp.StatusCode != 200 {
 		bodyBytes, err := ioutil.ReadAll(resp.Body)
 		if err != nil {
-			fmt.Println("Error reading body:", err)
-			return err
+			fmt.Fprintln(os.Stderr, "Error reading body:", err)
+			return classifyError(err)
 		}
 
-		fmt.Println("[pinging] Error response status code:", resp.StatusCode)
-		fmt.Println("[pinging] Response body:", string(bodyBytes))
-		return fmt.Errorf("[pinging] Error response status code: %d", resp.StatusCode)
+		// the controller refuses a digest it no longer knows
+		if resp.StatusCode == http.StatusUnauthorized || resp.StatusCode == http.StatusForbidden {
+			fmt.Fprintln(os.Stderr, "[pinging] Session expired, status code:", resp.StatusCode)
+			return &ResponseError{Code: codeSessionExpired, Message: fmt.Sprintf("[pinging] Session expired, status code: %d", resp.StatusCode), HTTPStatus: resp.StatusCode}
+		}
+		return httpStatusError("[pinging]", resp.StatusCode, bodyBytes)
 	}
 
 	return nil
 }
 
-func (c *Client) Logout(username string, passwordDigest string) error {
-	client := c.newUnsecureHTTPClient()
-
+func (c *Client) Logout(username string, passwordDigest string) *ResponseError {
 	body := []byte(fmt.Sprintf(`action=disconnect&login=%s&password_digest=%s`, url.QueryEscape(username), url.QueryEscape(passwordDigest)))
 
-	r, err := http.NewRequest("POST", loginURL, bytes.NewBuffer(body))
-	r.Header.Add("Content-Type", "application/x-www-form-urlencoded")
-
-	resp, err := client.Do(r)
+	resp, err := c.post(body)
 	if err != nil {
-		fmt.Println("Error making request:", err)
-		return err
+		fmt.Fprintln(os.Stderr, "Error making request:", err)
+		return classifyError(err)
 	}
 	defer resp.Body.Close()
 
 	if resp.StatusCode != 200 {
 		bodyBytes, err := ioutil.ReadAll(resp.Body)
 		if err != nil {
-			fmt.Println("Error reading body:", err)
-			return err
+			fmt.Fprintln(os.Stderr, "Error reading body:", err)
+			return classifyError(err)
 		}
 
-		fmt.Println("[logout] Error response status code:", resp.StatusCode)
-		fmt.Println("[logout] Response body:", string(bodyBytes))
-		return fmt.Errorf("[logout] Error response status code: %d", resp.StatusCode)
+		return httpStatusError("[logout]", resp.StatusCode, bodyBytes)
 	}
 
 	return nil
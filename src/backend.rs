@@ -0,0 +1,582 @@
+use miniserde::json;
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use wait_timeout::ChildExt;
+
+use crate::i18n::{fill, tr};
+use crate::mock::MockBackend;
+use crate::protocol::{self, Action, ErrorCode, Params, Payload, Request, Response, ResponseError};
+use crate::BACKEND_FILE_NAME;
+
+// Au-delà du délai du client HTTP du backend (15s, plus une relance immédiate), pour que son
+// erreur `timeout` arrive avant qu'on l'arrête
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(20);
+// Délai maximal de réponse à `--version`, au lancement
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const MOCK_PREFIX: &str = "mock";
+
+// Accès au contrôleur du réseau: `App` ne dépend que de ce trait.
+// Implémentations: `SubprocessBackend` (l'exécutable Go) et `MockBackend` (scénario scripté, pour
+// les tests et les démonstrations). Un client HTTP intégré pourra s'ajouter de la même manière.
+pub trait PortalBackend: Send {
+    fn login(&mut self, username: &str, password: &str) -> Result<Session, ResponseError>;
+    fn refresh(&mut self, session: &Session) -> Result<(), ResponseError>;
+    fn logout(&mut self, session: &Session) -> Result<(), ResponseError>;
+    fn status(&self) -> BackendStatus;
+}
+
+// Session ouverte sur le contrôleur
+#[derive(Clone)]
+pub struct Session {
+    pub username: String,
+    pub digest: String,
+}
+
+// Affiché sur l'écran de statut
+#[derive(Clone)]
+pub struct BackendStatus {
+    pub name: &'static str,
+    pub running: bool,
+    pub detail: String,
+}
+
+// `BACKEND_PATH=mock:<scénario>` remplace l'exécutable par un backend simulé (voir `MockBackend`).
+// `portalUrl` remplace l'adresse du contrôleur connue du backend.
+pub fn from_path(path: &str, portalUrl: Option<String>) -> Box<dyn PortalBackend> {
+    if path == MOCK_PREFIX {
+        return Box::new(MockBackend::parse(""));
+    }
+    if let Some(script) = path.strip_prefix("mock:") {
+        return Box::new(MockBackend::parse(script));
+    }
+    Box::new(SubprocessBackend::new(path.to_string(), portalUrl))
+}
+
+// D'où vient un emplacement possible du backend
+#[derive(Clone, Copy, PartialEq)]
+pub enum Origin {
+    Option,
+    Environment,
+    Config,
+    NextToProgram,
+    WorkingDirectory,
+}
+
+impl Origin {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Origin::Option => tr().originOption,
+            Origin::Environment => tr().originEnvironment,
+            Origin::Config => tr().originConfig,
+            Origin::NextToProgram => tr().originNextToProgram,
+            Origin::WorkingDirectory => tr().originWorkingDirectory,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Location {
+    pub origin: Origin,
+    pub path: String,
+}
+
+impl Location {
+    pub fn exists(&self) -> bool {
+        self.path.starts_with(MOCK_PREFIX) || Path::new(&self.path).is_file()
+    }
+}
+
+// Emplacements où chercher le backend, par ordre de priorité: un chemin donné par l'option, par
+// `BACKEND_PATH` ou par la configuration est le seul essayé; sinon, à côté du programme puis dans le
+// dossier courant
+pub fn locations(option: Option<String>, configured: Option<String>) -> Vec<Location> {
+    let explicit = [
+        (Origin::Option, option),
+        (Origin::Environment, env::var("BACKEND_PATH").ok()),
+        (Origin::Config, configured),
+    ];
+    if let Some((origin, Some(path))) = explicit.into_iter().find(|(_, path)| path.is_some()) {
+        return vec![Location { origin, path }];
+    }
+
+    let directories = [
+        (
+            Origin::NextToProgram,
+            env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf)),
+        ),
+        (Origin::WorkingDirectory, env::current_dir().ok()),
+    ];
+    let mut locations: Vec<Location> = Vec::new();
+    for (origin, directory) in directories {
+        if let Some(directory) = directory {
+            let path = directory
+                .join(BACKEND_FILE_NAME)
+                .to_string_lossy()
+                .to_string();
+            if !locations.iter().any(|location| location.path == path) {
+                locations.push(Location { origin, path });
+            }
+        }
+    }
+    locations
+}
+
+// Le premier emplacement où le backend existe, sinon le premier de la liste
+pub fn choose(locations: &[Location]) -> String {
+    locations
+        .iter()
+        .find(|location| location.exists())
+        .or(locations.first())
+        .map(|location| location.path.clone())
+        .unwrap_or_else(|| BACKEND_FILE_NAME.to_string())
+}
+
+// Vérifie au lancement que le backend existe, est exécutable et parle la même version du protocole:
+// `--version` répond par une réponse vide sur stdout
+pub fn check(path: &str) -> Result<(), ResponseError> {
+    if path.starts_with(MOCK_PREFIX) {
+        return Ok(());
+    }
+    let file = Path::new(path);
+    if !file.is_file() {
+        return Err(ResponseError::new(
+            ErrorCode::BackendMissing,
+            fill(tr().backendNotFound, &[&path]),
+        ));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = file
+            .metadata()
+            .map(|metadata| metadata.permissions().mode())
+            .unwrap_or(0);
+        if mode & 0o111 == 0 {
+            return Err(ResponseError::new(
+                ErrorCode::SpawnFailed,
+                fill(tr().backendNotExecutable, &[&path]),
+            ));
+        }
+    }
+
+    let mut child = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| {
+            ResponseError::new(
+                ErrorCode::SpawnFailed,
+                fill(tr().backendNotStartable, &[&path, &err]),
+            )
+        })?;
+    if !matches!(child.wait_timeout(PROBE_TIMEOUT), Ok(Some(_))) {
+        reap(&mut child);
+        return Err(ResponseError::new(
+            ErrorCode::BackendTimeout,
+            fill(tr().versionTimeout, &[&path, &PROBE_TIMEOUT.as_secs()]),
+        ));
+    }
+
+    let incompatible = |detail: String| {
+        ResponseError::new(
+            ErrorCode::IncompatibleBackend,
+            format!("{} --version: {}", path, detail),
+        )
+    };
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout
+            .read_to_string(&mut output)
+            .map_err(|err| incompatible(err.to_string()))?;
+    }
+    // un backend trop ancien ne connaît pas `--version` et répond par une erreur
+    let response = output
+        .lines()
+        .find_map(|line| json::from_str::<Response>(line.trim()).ok())
+        .ok_or_else(|| incompatible(tr().versionUnreadable.to_string()))?;
+    protocol::response_result(response)
+        .map(|_| ())
+        .map_err(|err| incompatible(err.message))
+}
+
+// Requête envoyée au thread du backend
+pub struct Job {
+    pub id: u64,
+    pub action: Action,
+    pub params: Params,
+}
+
+// Résultat renvoyé par le thread du backend à l'interface
+pub struct JobResult {
+    pub id: u64,
+    pub action: Action,
+    pub result: Result<Payload, ResponseError>,
+    // temps passé par le backend sur la requête, sans l'attente dans la file
+    pub duration: Duration,
+}
+
+// Les appels au backend se font dans un thread dédié, pour que l'interface ne soit jamais bloquée;
+// les résultats sont récupérés avec `poll`.
+pub struct BackendHandle {
+    jobs: Sender<Job>,
+    results: Receiver<JobResult>,
+    // requêtes refusées parce que le thread s'est arrêté (panique du backend)
+    failed: Vec<JobResult>,
+    status: Arc<Mutex<BackendStatus>>,
+    nextId: u64,
+}
+
+impl BackendHandle {
+    pub fn spawn(mut backend: Box<dyn PortalBackend>) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel::<JobResult>();
+        let status = Arc::new(Mutex::new(backend.status()));
+        let thread_status = status.clone();
+
+        thread::spawn(move || {
+            for job in job_receiver {
+                let started = Instant::now();
+                let result = run_job(backend.as_mut(), job.action, job.params);
+                if let Ok(mut status) = thread_status.lock() {
+                    *status = backend.status();
+                }
+
+                let sent = result_sender.send(JobResult {
+                    id: job.id,
+                    action: job.action,
+                    result,
+                    duration: started.elapsed(),
+                });
+                if sent.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            results,
+            failed: Vec::new(),
+            status,
+            nextId: 0,
+        }
+    }
+
+    // État du backend après la dernière requête traitée
+    pub fn status(&self) -> Option<BackendStatus> {
+        self.status.lock().ok().map(|status| status.clone())
+    }
+
+    pub fn submit(&mut self, action: Action, params: Params) -> u64 {
+        self.nextId += 1;
+        let id = self.nextId;
+        if self.jobs.send(Job { id, action, params }).is_err() {
+            self.failed.push(JobResult {
+                id,
+                action,
+                result: Err(ResponseError::new(
+                    ErrorCode::BackendUnavailable,
+                    tr().backendThreadStopped,
+                )),
+                duration: Duration::ZERO,
+            });
+        }
+        id
+    }
+
+    pub fn poll(&mut self) -> Option<JobResult> {
+        if let Some(result) = self.failed.pop() {
+            return Some(result);
+        }
+        match self.results.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+fn run_job(
+    backend: &mut dyn PortalBackend,
+    action: Action,
+    params: Params,
+) -> Result<Payload, ResponseError> {
+    if action == Action::Login {
+        let session = backend.login(
+            params.username.as_deref().unwrap_or_default(),
+            params.password.as_deref().unwrap_or_default(),
+        )?;
+        return Ok(Payload {
+            username: Some(session.username),
+            digest: Some(session.digest),
+        });
+    }
+
+    let session = match (params.username, params.digest) {
+        (Some(username), Some(digest)) => Session { username, digest },
+        _ => {
+            return Err(ResponseError::new(
+                ErrorCode::InvalidRequest,
+                tr().noOpenSession,
+            ))
+        }
+    };
+
+    match action {
+        Action::Ping => backend.refresh(&session),
+        _ => backend.logout(&session),
+    }
+    .map(|_| Payload::default())
+}
+
+// Backend par défaut: l'exécutable Go, gardé ouvert entre les requêtes
+pub struct SubprocessBackend {
+    worker: Worker,
+    requestId: u64,
+}
+
+impl SubprocessBackend {
+    pub fn new(path: String, portalUrl: Option<String>) -> Self {
+        Self {
+            worker: Worker::new(path, portalUrl),
+            requestId: 0,
+        }
+    }
+
+    fn call(&mut self, action: Action, params: Params) -> Result<Payload, ResponseError> {
+        self.requestId += 1;
+        self.worker
+            .call(&Request::new(self.requestId, action, params))
+    }
+
+    fn session_params(session: &Session) -> Params {
+        Params {
+            username: Some(session.username.clone()),
+            digest: Some(session.digest.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+impl PortalBackend for SubprocessBackend {
+    fn login(&mut self, username: &str, password: &str) -> Result<Session, ResponseError> {
+        let payload = self.call(
+            Action::Login,
+            Params {
+                username: Some(username.to_string()),
+                password: Some(password.to_string()),
+                ..Default::default()
+            },
+        )?;
+
+        match payload.digest {
+            Some(digest) => Ok(Session {
+                username: payload.username.unwrap_or_else(|| username.to_string()),
+                digest,
+            }),
+            None => Err(ResponseError::new(
+                ErrorCode::MalformedResponse,
+                tr().loginWithoutDigest,
+            )),
+        }
+    }
+
+    fn refresh(&mut self, session: &Session) -> Result<(), ResponseError> {
+        self.call(Action::Ping, Self::session_params(session))
+            .map(|_| ())
+    }
+
+    fn logout(&mut self, session: &Session) -> Result<(), ResponseError> {
+        self.call(Action::Logout, Self::session_params(session))
+            .map(|_| ())
+    }
+
+    fn status(&self) -> BackendStatus {
+        BackendStatus {
+            name: "subprocess",
+            running: self.worker.process.is_some(),
+            detail: match &self.worker.process {
+                Some(process) => format!("{} (pid {})", self.worker.path, process.child.id()),
+                None => self.worker.path.clone(),
+            },
+        }
+    }
+}
+
+// Processus backend lancé une seule fois (`--worker`) et réutilisé pour toutes les requêtes;
+// il est relancé automatiquement s'il s'arrête ou ne répond plus.
+pub struct Worker {
+    path: String,
+    // transmis au backend dans PORTAL_URL
+    portalUrl: Option<String>,
+    process: Option<WorkerProcess>,
+}
+
+struct WorkerProcess {
+    child: Child,
+    stdin: ChildStdin,
+    // lignes lues sur stdout par un thread dédié, pour pouvoir attendre avec un délai maximal
+    lines: Receiver<String>,
+}
+
+enum CallError {
+    // le processus est mort ou le pipe est cassé: on peut relancer et réessayer
+    Died(String),
+    Failed(ResponseError),
+}
+
+impl Worker {
+    pub fn new(path: String, portalUrl: Option<String>) -> Self {
+        Self {
+            path,
+            portalUrl,
+            process: None,
+        }
+    }
+
+    pub fn call(&mut self, request: &Request) -> Result<Payload, ResponseError> {
+        match self.try_call(request) {
+            Err(CallError::Died(_)) => {
+                // une seule relance par requête, pour ne pas boucler sur un backend cassé
+                self.stop();
+                match self.try_call(request) {
+                    Ok(payload) => Ok(payload),
+                    Err(CallError::Died(message)) => {
+                        self.stop();
+                        Err(ResponseError::new(ErrorCode::BackendUnavailable, message))
+                    }
+                    Err(CallError::Failed(err)) => Err(err),
+                }
+            }
+            Err(CallError::Failed(err)) => Err(err),
+            Ok(payload) => Ok(payload),
+        }
+    }
+
+    fn try_call(&mut self, request: &Request) -> Result<Payload, CallError> {
+        if self.process.is_none() {
+            self.process = Some(self.start().map_err(CallError::Failed)?);
+        }
+        let process = self.process.as_mut().unwrap();
+
+        if let Err(err) = process
+            .stdin
+            .write_all(request.encode().as_bytes())
+            .and_then(|_| process.stdin.flush())
+        {
+            return Err(CallError::Died(fill(tr().requestNotWritten, &[&err])));
+        }
+
+        let deadline = Instant::now() + BACKEND_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match process.lines.recv_timeout(remaining) {
+                Ok(line) => {
+                    // les lignes qui ne sont pas la réponse attendue (logs, réponse à une requête
+                    // abandonnée) sont ignorées
+                    if let Ok(response) = json::from_str::<Response>(line.trim()) {
+                        if response.id == request.id {
+                            return protocol::response_result(response).map_err(CallError::Failed);
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    // un backend bloqué est arrêté; il sera relancé à la prochaine requête
+                    self.stop();
+                    return Err(CallError::Failed(ResponseError::new(
+                        ErrorCode::BackendTimeout,
+                        fill(tr().backendNoAnswer, &[&BACKEND_TIMEOUT.as_secs()]),
+                    )));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(CallError::Died(tr().backendExited.to_string()));
+                }
+            }
+        }
+    }
+
+    fn start(&self) -> Result<WorkerProcess, ResponseError> {
+        let mut command = Command::new(&self.path);
+        if let Some(url) = &self.portalUrl {
+            command.env("PORTAL_URL", url);
+        }
+        let mut child = command
+            .arg("--worker")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| {
+                let code = match err.kind() {
+                    io::ErrorKind::NotFound => ErrorCode::BackendMissing,
+                    _ => ErrorCode::SpawnFailed,
+                };
+                ResponseError::new(code, fill(tr().backendNotStartable, &[&self.path, &err]))
+            })?;
+
+        let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => {
+                reap(&mut child);
+                return Err(ResponseError::new(
+                    ErrorCode::SpawnFailed,
+                    tr().backendNoPipes,
+                ));
+            }
+        };
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(WorkerProcess {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            reap(&mut process.child);
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            // fermer stdin suffit à arrêter le backend proprement
+            drop(process.stdin);
+            if let Ok(None) = process.child.wait_timeout(Duration::from_secs(1)) {
+                reap(&mut process.child);
+            }
+        }
+    }
+}
+
+// Arrête le processus et attend sa fin. `kill` n'échoue que si le processus s'est déjà arrêté, et
+// `wait` que s'il a déjà été attendu: dans les deux cas il n'y a plus rien à faire.
+fn reap(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -0,0 +1,72 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+// Délais entre les tentatives de reconnexion: ils doublent à chaque échec, sans dépasser `ceiling`,
+// et sont tirés au hasard dans la seconde moitié de l'intervalle pour que les machines d'une même
+// chambre ne se reconnectent pas toutes en même temps.
+pub struct Backoff {
+    base: Duration,
+    ceiling: Duration,
+    attempt: u32,
+    next: Option<Instant>,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, ceiling: Duration) -> Self {
+        Self {
+            base,
+            ceiling: ceiling.max(base),
+            attempt: 0,
+            next: None,
+        }
+    }
+
+    // Programme la tentative suivante et renvoie le délai choisi
+    pub fn schedule(&mut self) -> Duration {
+        self.attempt += 1;
+
+        let exponent = (self.attempt - 1).min(16);
+        let delay = self.base.saturating_mul(1 << exponent).min(self.ceiling);
+        let half = delay / 2;
+        let jitter = Duration::from_millis(random() % (half.as_millis() as u64 + 1));
+        let delay = half + jitter;
+
+        self.next = Some(Instant::now() + delay);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.next = None;
+    }
+
+    // Nombre de tentatives programmées depuis la dernière réussite
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.next.is_some()
+    }
+
+    pub fn is_due(&self) -> bool {
+        matches!(self.next, Some(next) if Instant::now() >= next)
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.next
+            .map(|next| next.saturating_duration_since(Instant::now()))
+    }
+
+    // La tentative programmée est en cours: on garde le compteur, mais plus d'échéance
+    pub fn start_attempt(&mut self) {
+        self.next = None;
+    }
+}
+
+// Pas besoin d'un vrai générateur aléatoire pour étaler des reconnexions: chaque `RandomState`
+// reçoit des clés différentes, tirées au hasard au démarrage du programme.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
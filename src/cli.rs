@@ -0,0 +1,569 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::backend;
+use crate::config::{self, Config, ConfigError, PASSPHRASE_VARIABLE};
+use crate::connection::{describe_error, Connection, ConnectionEvent};
+use crate::control::{self, ControlCommand, ControlReply};
+use crate::eventlog::EventLog;
+use crate::i18n::{self, fill, tr};
+use crate::instance::{self, LockError};
+use crate::session;
+use crate::{getReconnectMaxDelay, PING_INTERVAL};
+
+// Délai maximal d'une connexion ou d'une déconnexion lancée depuis la ligne de commande
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Command {
+    Tui,
+    Connect,
+    Disconnect,
+    Status,
+    SetCredentials,
+    ForgetCredentials,
+    SetPassphrase,
+    Control(ControlCommand),
+    Help,
+    Version,
+}
+
+pub struct Cli {
+    pub command: Command,
+    pub config: Option<PathBuf>,
+    pub backend: Option<String>,
+    pub interval: Option<i64>,
+    pub username: Option<String>,
+    pub socket: Option<PathBuf>,
+    pub json: bool,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self {
+            command: Command::Tui,
+            config: None,
+            backend: None,
+            interval: None,
+            username: None,
+            socket: None,
+            json: false,
+        };
+        let mut command: Option<Command> = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // `--option valeur` et `--option=valeur` sont acceptés
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| -> Result<String, String> {
+                match inline {
+                    Some(value) => Ok(value.to_string()),
+                    None => args
+                        .next()
+                        .ok_or_else(|| fill(tr().optionNeedsValue, &[&name])),
+                }
+            };
+
+            let next = match name.as_str() {
+                "--config" => {
+                    cli.config = Some(PathBuf::from(value(&name)?));
+                    None
+                }
+                "--backend" => {
+                    cli.backend = Some(value(&name)?);
+                    None
+                }
+                "--interval" => {
+                    let interval = value(&name)?;
+                    cli.interval = match interval.parse::<i64>() {
+                        Ok(seconds) if seconds > 0 => Some(seconds),
+                        _ => return Err(fill(tr().invalidInterval, &[&interval])),
+                    };
+                    None
+                }
+                "--username" => {
+                    cli.username = Some(value(&name)?);
+                    None
+                }
+                "--socket" => {
+                    cli.socket = Some(PathBuf::from(value(&name)?));
+                    None
+                }
+                "--json" => {
+                    cli.json = true;
+                    None
+                }
+                "-h" | "--help" => Some(Command::Help),
+                "-V" | "--version" => Some(Command::Version),
+                "--daemon" | "--headless" | "connect" => Some(Command::Connect),
+                "disconnect" => Some(Command::Disconnect),
+                "status" => Some(Command::Status),
+                "set-credentials" => Some(Command::SetCredentials),
+                "forget-credentials" => Some(Command::ForgetCredentials),
+                "set-passphrase" => Some(Command::SetPassphrase),
+                "control" => {
+                    let order = args
+                        .next()
+                        .ok_or_else(|| tr().controlNeedsOrder.to_string())?;
+                    match ControlCommand::parse(&order) {
+                        Some(order) => Some(Command::Control(order)),
+                        None => return Err(fill(tr().unknownOrder, &[&order])),
+                    }
+                }
+                other => return Err(fill(tr().unknownArgument, &[&other])),
+            };
+
+            if let Some(next) = next {
+                match command {
+                    // --help et --version l'emportent sur la commande
+                    Some(Command::Help) | Some(Command::Version) => {}
+                    Some(_) if next != Command::Help && next != Command::Version => {
+                        return Err(tr().oneCommandOnly.to_string())
+                    }
+                    _ => command = Some(next),
+                }
+            }
+        }
+
+        cli.command = command.unwrap_or(Command::Tui);
+        Ok(cli)
+    }
+
+    // Lit la configuration, et choisit la langue des messages d'après son réglage `language`
+    pub fn load_config(&self) -> Result<Config, ConfigError> {
+        self.init_language();
+        match &self.config {
+            Some(path) => Config::load(path.clone()),
+            None => Config::init(),
+        }
+    }
+
+    // Comme `load_config`, sans rien écrire dans la configuration
+    pub fn read_config(&self) -> Result<Config, ConfigError> {
+        self.init_language();
+        match &self.config {
+            Some(path) => Config::read_from(path.clone()),
+            None => Config::read(),
+        }
+    }
+
+    // Avant de lire la configuration, dont le chargement produit déjà des messages; l'ancien
+    // emplacement n'a jamais eu de réglage de langue
+    fn init_language(&self) {
+        let path = self.config.clone().unwrap_or_else(config::get_config_path);
+        i18n::init(config::configured_language(&path).as_deref());
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        self.socket
+            .clone()
+            .unwrap_or_else(|| control::socket_path(&self.config_dir()))
+    }
+
+    // Dossier de la configuration, comme `Config::dir`, sans lire le fichier
+    fn config_dir(&self) -> PathBuf {
+        match &self.config {
+            Some(path) => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            None => config::get_config_dir(),
+        }
+    }
+
+    // Par ordre de priorité: option, variable d'environnement, configuration, emplacements par défaut
+    pub fn backend_locations(&self, config: &Config) -> Vec<backend::Location> {
+        backend::locations(self.backend.clone(), config.backendPath.clone())
+    }
+
+    pub fn backend_path(&self, config: &Config) -> String {
+        backend::choose(&self.backend_locations(config))
+    }
+
+    pub fn ping_interval(&self, config: &Config) -> i64 {
+        self.interval
+            .or(config.pingInterval)
+            .unwrap_or(PING_INTERVAL)
+    }
+
+    pub fn connection(&self, config: &Config) -> Connection {
+        let mut connection = Connection::new(
+            backend::from_path(&self.backend_path(config), config.portalUrl.clone()),
+            Duration::from_secs(getReconnectMaxDelay(config.reconnectMaxDelay)),
+        );
+        connection.pingInterval = self.ping_interval(config);
+        connection.keep_state_in(config.dir());
+        connection
+    }
+
+    // Identifiants à utiliser: ceux sauvegardés, sauf si `--username` en désigne d'autres, auquel cas
+    // le mot de passe est demandé dans le terminal
+    pub fn credentials(&self, config: &mut Config) -> Result<(String, String), String> {
+        let username = self
+            .username
+            .clone()
+            .unwrap_or_else(|| config.username.clone());
+        if username.is_empty() {
+            return Err(tr().noSavedUsername.to_string());
+        }
+
+        if username == config.username {
+            unlock(config)?;
+        }
+        if username == config.username && !config.password.is_empty() {
+            return Ok((username, config.password.clone()));
+        }
+        if !io::stdin().is_terminal() {
+            return Err(fill(tr().noSavedPassword, &[&username]));
+        }
+        let password =
+            read_secret(&fill(tr().passwordPrompt, &[&username])).map_err(|err| err.to_string())?;
+        Ok((username, password))
+    }
+}
+
+// Exécute les commandes ponctuelles; renvoie le code de sortie du programme
+pub fn run(cli: &Cli) -> i32 {
+    let result = match cli.command {
+        Command::Status => status(cli),
+        Command::Disconnect => disconnect(cli),
+        Command::SetCredentials => set_credentials(cli),
+        Command::ForgetCredentials => forget_credentials(cli),
+        Command::SetPassphrase => set_passphrase(cli),
+        Command::Control(order) => control(cli, order),
+        _ => Ok(()),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("{}", err);
+            }
+            1
+        }
+    }
+}
+
+fn status(cli: &Cli) -> Result<(), String> {
+    // lecture seule: une migration en attente est laissée au prochain lancement de l'interface,
+    // qui l'affichera
+    let config = cli.read_config().map_err(|err| err.to_string())?;
+    let backendPath = cli.backend_path(&config);
+
+    println!("ConnectionInternat {}", config.version);
+    println!("Configuration: {}", config.path.display());
+    if let Some(update) = &config.update {
+        println!("{}", fill(tr().pendingUpdate, &[&update.from]));
+        for change in &update.changes {
+            println!("  - {}", change);
+        }
+    }
+    if config.username.is_empty() {
+        println!("{}", tr().noSavedUsernameStatus);
+    } else {
+        let password = match (
+            config.is_vault(),
+            config.in_keyring(),
+            config.password.is_empty(),
+        ) {
+            (true, _, _) => tr().passwordEncrypted,
+            (false, true, _) => tr().passwordInKeyring,
+            (false, false, true) => tr().withoutPassword,
+            (false, false, false) => tr().withPassword,
+        };
+        println!(
+            "{}",
+            fill(tr().savedUsername, &[&config.username, &password])
+        );
+    }
+    match session::load(&session::path(&config.dir())) {
+        Some(saved) => println!(
+            "{}",
+            fill(tr().savedSession, &[&saved.username, &saved.lastLogin])
+        ),
+        None => println!("{}", tr().noSavedSession),
+    }
+    let pending = session::load_pending(&session::pending_path(&config.dir()));
+    if pending.is_empty() {
+        println!("{}", tr().noPendingLogouts);
+    } else {
+        println!("{}", tr().pendingLogoutsHeader);
+        for logout in pending {
+            println!(
+                "{}",
+                fill(tr().pendingLogoutSince, &[&logout.username, &logout.since])
+            );
+        }
+    }
+    if let Some(err) = &config.keyringError {
+        println!("{}", fill(tr().keyringNotice, &[err]));
+    }
+    println!("Backend: {}", backendPath);
+    if let Err(err) = backend::check(&backendPath) {
+        println!("  {}", describe_error(&err));
+        println!("  {}", tr().searchedLocations);
+        for location in cli.backend_locations(&config) {
+            println!("    {} ({})", location.path, location.origin.label());
+        }
+    }
+    println!(
+        "{}",
+        fill(
+            tr().eventLogPath,
+            &[&EventLog::new(&config.dir()).path().display()]
+        )
+    );
+    println!(
+        "{}",
+        fill(tr().controlSocket, &[&cli.socket_path().display()])
+    );
+    println!(
+        "{}",
+        fill(tr().pingInterval, &[&cli.ping_interval(&config)])
+    );
+    if let Some(url) = &config.portalUrl {
+        println!("{}", fill(tr().controller, &[url]));
+    }
+    let autoConnect = match config.autoConnect {
+        true => tr().yes,
+        false => tr().no,
+    };
+    println!("{}", fill(tr().connectOnStart, &[&autoConnect]));
+    Ok(())
+}
+
+// La session sauvegardée au dernier lancement est fermée directement. Sans elle, le seul moyen
+// d'obtenir le digest de cet appareil est de se reconnecter: on ouvre donc la session pour la
+// refermer aussitôt
+fn disconnect(cli: &Cli) -> Result<(), String> {
+    let mut config = cli.load_config().map_err(|err| err.to_string())?;
+    // une instance lancée ferme elle-même sa session; sinon aucune ne démarre pendant la fermeture
+    let _lock = match instance::acquire(&config.dir(), "disconnect") {
+        Ok((lock, _)) => lock,
+        Err(LockError::Held(_)) => return control(cli, ControlCommand::Disconnect),
+        Err(err) => return Err(err.to_string()),
+    };
+    let mut connection = cli.connection(&config);
+    let username = cli
+        .username
+        .clone()
+        .unwrap_or_else(|| config.username.clone());
+
+    if let Some(saved) = connection.saved_session(&username) {
+        connection.restore(saved);
+    } else {
+        let (username, password) = cli.credentials(&mut config)?;
+        connection.login(username, password);
+        connection.wait(COMMAND_TIMEOUT);
+    }
+    if connection.passwordDigest.is_none() {
+        let error = connection
+            .lastError
+            .as_ref()
+            .map(describe_error)
+            .unwrap_or_default();
+        return Err(fill(tr().sessionUnavailable, &[&error]));
+    }
+
+    connection.disconnect();
+    for event in connection.wait(COMMAND_TIMEOUT) {
+        match event {
+            ConnectionEvent::LoggedOut(Ok(())) => {
+                println!("{}", tr().sessionClosed);
+                return Ok(());
+            }
+            ConnectionEvent::LoggedOut(Err(err)) => {
+                return Err(fill(tr().logoutFailed, &[&describe_error(&err)]))
+            }
+            _ => {}
+        }
+    }
+    Err(tr().logoutTimedOut.to_string())
+}
+
+fn set_credentials(cli: &Cli) -> Result<(), String> {
+    let mut config = cli.load_config().map_err(|err| err.to_string())?;
+    // en mode coffre, le nouveau mot de passe est chiffré avec la même phrase de passe
+    unlock(&mut config)?;
+
+    let username = match &cli.username {
+        Some(username) => username.clone(),
+        None => read_line(tr().usernamePrompt).map_err(|err| err.to_string())?,
+    };
+    if username.is_empty() {
+        return Err(tr().emptyUsername.to_string());
+    }
+    let password =
+        read_secret(&fill(tr().passwordPrompt, &[&username])).map_err(|err| err.to_string())?;
+
+    config.username = username;
+    config.password = password;
+    config.save().map_err(|err| err.to_string())?;
+    println!("{}", fill(tr().credentialsSaved, &[&config.path.display()]));
+    if config.in_keyring() {
+        println!("{}", tr().passwordStoredInKeyring);
+    } else if let Some(err) = &config.keyringError {
+        println!("{}", fill(tr().keyringNotice, &[err]));
+    }
+    Ok(())
+}
+
+fn forget_credentials(cli: &Cli) -> Result<(), String> {
+    let mut config = cli.load_config().map_err(|err| err.to_string())?;
+    config.forget_credentials();
+    config.save().map_err(|err| err.to_string())?;
+    if let Some(err) = &config.keyringError {
+        println!("{}", fill(tr().keyringNotice, &[err]));
+    }
+    println!("{}", tr().credentialsForgotten);
+    Ok(())
+}
+
+fn set_passphrase(cli: &Cli) -> Result<(), String> {
+    let mut config = cli.load_config().map_err(|err| err.to_string())?;
+    unlock(&mut config)?;
+    if config.password.is_empty() {
+        return Err(tr().nothingToEncrypt.to_string());
+    }
+
+    let passphrase = read_secret(tr().newPassphrasePrompt).map_err(|err| err.to_string())?;
+    if !passphrase.is_empty() {
+        let confirmation =
+            read_secret(tr().confirmPassphrasePrompt).map_err(|err| err.to_string())?;
+        if confirmation != passphrase {
+            return Err(tr().passphraseMismatch.to_string());
+        }
+    }
+
+    let enabled = !passphrase.is_empty();
+    config.set_passphrase(if enabled { Some(passphrase) } else { None });
+    config.save().map_err(|err| err.to_string())?;
+    println!(
+        "{}",
+        match enabled {
+            true => tr().passwordSealed,
+            false => tr().passwordInClear,
+        }
+    );
+    Ok(())
+}
+
+// Demande la phrase de passe d'une configuration verrouillée
+fn unlock(config: &mut Config) -> Result<(), String> {
+    if !config.is_locked() {
+        return Ok(());
+    }
+    let passphrase = read_secret(tr().passphrasePrompt).map_err(|err| err.to_string())?;
+    if passphrase.is_empty() {
+        // pas de terminal (service, daemon): la phrase de passe passe par l'environnement
+        return Err(fill(tr().passphraseRequired, &[&PASSPHRASE_VARIABLE]));
+    }
+    config.unlock(&passphrase).map_err(|err| err.to_string())
+}
+
+fn control(cli: &Cli, order: ControlCommand) -> Result<(), String> {
+    // sans charger la configuration, mais dans sa langue
+    cli.init_language();
+    let reply = control::send(&cli.socket_path(), order).map_err(|err| err.to_string())?;
+    print_reply(cli, &reply);
+
+    match reply.ok {
+        true => Ok(()),
+        // le message est déjà affiché
+        false => Err(String::new()),
+    }
+}
+
+// Une autre instance tient le verrou: plutôt que d'ouvrir une seconde session, on affiche son état
+// par le socket de contrôle; renvoie le code de sortie du programme
+pub fn attach(cli: &Cli, err: LockError) -> i32 {
+    eprintln!("{}", err);
+    if !matches!(err, LockError::Held(_)) {
+        return 1;
+    }
+    match control::send(&cli.socket_path(), ControlCommand::Status) {
+        Ok(reply) => {
+            print_reply(cli, &reply);
+            eprintln!("{}", tr().attachHint);
+            0
+        }
+        Err(_) => {
+            eprintln!("{}", tr().attachUnreachable);
+            1
+        }
+    }
+}
+
+fn print_reply(cli: &Cli, reply: &ControlReply) {
+    if cli.json {
+        println!("{}", miniserde::json::to_string(&reply));
+    } else {
+        if let Some(message) = &reply.message {
+            println!("{}", message);
+        }
+        println!("{}", fill(tr().replyStatus, &[&reply.status]));
+        let lines = [
+            (tr().replyUsername, &reply.username),
+            (tr().replyLastLogin, &reply.lastLogin),
+            (tr().replyLastPing, &reply.lastPingTimestamp),
+        ];
+        for (label, value) in lines {
+            if let Some(value) = value {
+                println!("{}: {}", label, value);
+            }
+        }
+        if let Some(error) = &reply.lastError {
+            println!(
+                "{}",
+                fill(tr().replyLastError, &[&error.replace('\n', " ")])
+            );
+        }
+    }
+}
+
+fn read_line(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// Lit un mot de passe sans l'afficher quand l'entrée est un terminal
+fn read_secret(prompt: &str) -> io::Result<String> {
+    if !io::stdin().is_terminal() {
+        return read_line(prompt);
+    }
+
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    terminal::enable_raw_mode()?;
+    let mut value = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Backspace => {
+                    value.pop();
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(io::Error::new(io::ErrorKind::Interrupted, tr().cancelled))
+                }
+                KeyCode::Char(c) => value.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(err) => break Err(err),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    eprintln!();
+
+    result.map(|_| value)
+}
@@ -1,16 +1,20 @@
 use ratatui::{
     buffer::Buffer,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
-    layout::{Constraint, Layout, Position, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::{Position, Rect},
+    style::{Color, Style, Stylize},
     symbols,
-    text::{Line, Span, Text},
+    text::{Line, Span},
     widgets::{
         Block, Borders, HighlightSpacing, List, ListDirection, ListItem, ListState, Paragraph,
         StatefulWidget, Widget,
     },
-    DefaultTerminal, Frame,
+    Frame,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::i18n::tr;
 
 // Composant d'entrée de texte
 pub enum InputMode {
@@ -23,11 +27,19 @@ pub enum Offset {
     Right,
 }
 
+// Caractère affiché à la place de chaque graphème d'un champ secret
+const MASK: char = '•';
+// Touche qui affiche ou masque un champ secret
+const REVEAL_KEY: KeyCode = KeyCode::F(2);
+
 pub struct Input {
     pub label: String,
     pub value: String,   // Valeur
     pub mode: InputMode, // Mode
-    index: usize,        // Index du curseur
+    index: usize,        // Index du curseur, en graphèmes
+    secret: bool,        // Valeur masquée à l'affichage
+    revealed: bool,      // Valeur secrète affichée jusqu'à la validation
+    scroll: usize,       // Première colonne affichée, quand la valeur dépasse du champ
 }
 
 impl Input {
@@ -40,76 +52,210 @@ impl Input {
                 false => InputMode::Normal,
             },
             index: 0,
+            secret: false,
+            revealed: false,
+            scroll: 0,
+        }
+    }
+
+    // Champ pour un mot de passe: un masque par graphème, F2 pour afficher la valeur
+    pub fn secret(label: &str, focus: bool) -> Self {
+        Self {
+            secret: true,
+            ..Self::new(label, focus)
         }
     }
 
+    fn masked(&self) -> bool {
+        self.secret && !self.revealed
+    }
+
+    fn len(&self) -> usize {
+        self.value.graphemes(true).count()
+    }
+
     fn move_cursor(&mut self, offset: Offset) {
         let cursor_move = match offset {
             Offset::Left => self.index.saturating_sub(1),
             Offset::Right => self.index.saturating_add(1),
         };
-        self.index = cursor_move.clamp(0, self.value.chars().count())
+        self.index = cursor_move.clamp(0, self.len())
+    }
+
+    // Début du mot avant le curseur (ou sous le curseur), les mots étant séparés par des espaces.
+    // Un champ masqué compte pour un seul mot, pour ne rien laisser deviner de la valeur.
+    fn word_start(&self) -> usize {
+        if self.masked() {
+            return 0;
+        }
+        let graphemes: Vec<&str> = self.value.graphemes(true).collect();
+        let mut index = self.index;
+        while index > 0 && graphemes[index - 1].trim().is_empty() {
+            index -= 1;
+        }
+        while index > 0 && !graphemes[index - 1].trim().is_empty() {
+            index -= 1;
+        }
+        index
+    }
+
+    // Fin du mot après le curseur
+    fn word_end(&self) -> usize {
+        let graphemes: Vec<&str> = self.value.graphemes(true).collect();
+        if self.masked() {
+            return graphemes.len();
+        }
+        let mut index = self.index;
+        while index < graphemes.len() && graphemes[index].trim().is_empty() {
+            index += 1;
+        }
+        while index < graphemes.len() && !graphemes[index].trim().is_empty() {
+            index += 1;
+        }
+        index
     }
 
-    fn enter_char(&mut self, new_char: char) {
-        let index = self.byte_index();
-        self.value.insert(index, new_char);
-        self.move_cursor(Offset::Right);
+    // Un caractère combinant s'ajoute au graphème précédent: le curseur reste après ce graphème
+    fn enter_str(&mut self, text: &str) {
+        let index = self.byte_index(self.index);
+        self.value.insert_str(index, text);
+        let inserted_end = index + text.len();
+        self.index = self.value[..inserted_end].graphemes(true).count();
+    }
+
+    // Supprime les graphèmes de `start` (inclus) à `end` (exclu); le curseur va à `start`
+    fn delete_range(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        let range = self.byte_index(start)..self.byte_index(end);
+        self.value.replace_range(range, "");
+        self.index = start;
     }
 
     fn delete_char(&mut self) {
         let is_not_cursor_leftmost = self.index != 0;
         if is_not_cursor_leftmost {
-            let current_index = self.index;
-            let from_left_to_current_index = current_index - 1;
-            let before_char_to_delete = self.value.chars().take(from_left_to_current_index);
-            let after_char_to_delete = self.value.chars().skip(current_index);
-
-            self.value = before_char_to_delete.chain(after_char_to_delete).collect();
-            self.move_cursor(Offset::Left);
+            self.delete_range(self.index - 1, self.index);
         }
     }
 
-    fn byte_index(&self) -> usize {
+    // Position en octets du graphème numéro `index`
+    fn byte_index(&self, index: usize) -> usize {
         self.value
-            .char_indices()
+            .grapheme_indices(true)
             .map(|(i, _)| i)
-            .nth(self.index)
+            .nth(index)
             .unwrap_or(self.value.len())
     }
 
+    // Texte affiché dans le champ
+    fn display_value(&self) -> String {
+        match self.masked() {
+            true => self.value.graphemes(true).map(|_| MASK).collect(),
+            false => self.value.clone(),
+        }
+    }
+
+    // Colonne du curseur dans le texte affiché: les caractères larges (CJK, emoji) en prennent deux
+    fn cursor_column(&self) -> usize {
+        match self.masked() {
+            true => self.index,
+            false => self.value[..self.byte_index(self.index)].width(),
+        }
+    }
+
     pub fn reset_cursor(&mut self) {
         self.index = 0;
+        self.scroll = 0;
+    }
+
+    // Remplit le champ, curseur à la fin
+    pub fn set_value(&mut self, value: String) {
+        self.value = value;
+        self.index = self.len();
     }
 
     pub fn submit_input(&mut self) {
         self.value.clear();
         self.reset_cursor();
+        self.revealed = false;
+    }
+
+    // Texte collé (collage délimité du terminal): inséré d'un bloc, sans les retours à la ligne
+    pub fn handle_paste(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        self.enter_str(&text);
     }
 
-    pub fn handle_key(&mut self, code: KeyCode) -> bool {
-        match code {
-            KeyCode::Char(to_insert) => self.enter_char(to_insert),
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        // AltGr arrive comme Ctrl+Alt sous Windows: les deux ensemble tapent un caractère (@, €...)
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let (control, alt) = (control && !alt, alt && !control);
+        match key.code {
+            // raccourcis de readline
+            KeyCode::Char('a') if control => self.index = 0,
+            KeyCode::Char('e') if control => self.index = self.len(),
+            KeyCode::Char('w') if control => self.delete_range(self.word_start(), self.index),
+            KeyCode::Char('u') if control => self.delete_range(0, self.index),
+            KeyCode::Char('k') if control => {
+                let index = self.index;
+                self.delete_range(index, self.len());
+            }
+            KeyCode::Char('b') if alt => self.index = self.word_start(),
+            KeyCode::Char('f') if alt => self.index = self.word_end(),
+            KeyCode::Char(_) if control || alt => {}
+            KeyCode::Char(to_insert) => self.enter_str(to_insert.encode_utf8(&mut [0; 4])),
+
             KeyCode::Backspace => self.delete_char(),
+            KeyCode::Delete => self.delete_range(self.index, self.index + 1),
+            KeyCode::Left if alt => self.index = self.word_start(),
+            KeyCode::Right if alt => self.index = self.word_end(),
             KeyCode::Left => self.move_cursor(Offset::Left),
             KeyCode::Right => self.move_cursor(Offset::Right),
+            KeyCode::Home => self.index = 0,
+            KeyCode::End => self.index = self.len(),
+            REVEAL_KEY if self.secret => self.revealed = !self.revealed,
 
             KeyCode::Enter => {
                 self.mode = InputMode::Normal;
+                self.revealed = false;
                 return true;
             }
             _ => {}
         }
-        return false;
+        false
+    }
+
+    // Fait défiler le champ pour que le curseur reste visible entre les bordures
+    fn scroll_to_cursor(&mut self, width: usize) {
+        let column = self.cursor_column();
+        let total = self.display_value().width();
+        if column < self.scroll {
+            self.scroll = column;
+        } else if column >= self.scroll + width {
+            self.scroll = column + 1 - width;
+        }
+        // la valeur a raccourci: pas de place perdue à droite
+        self.scroll = self.scroll.min((total + 1).saturating_sub(width));
     }
 
     pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
-        let input = Paragraph::new(self.value.as_str())
+        self.scroll_to_cursor(area.width.saturating_sub(2).max(1) as usize);
+
+        let title = match (self.secret, &self.mode, self.revealed) {
+            (true, InputMode::Editing, false) => format!("{} ({})", self.label, tr().revealHint),
+            (true, InputMode::Editing, true) => format!("{} ({})", self.label, tr().hideHint),
+            _ => self.label.clone(),
+        };
+        let input = Paragraph::new(self.display_value())
             .style(match self.mode {
                 InputMode::Normal => Style::default(),
                 InputMode::Editing => Style::default().fg(Color::Yellow),
             })
-            .block(Block::bordered().title(self.label.as_str()));
+            .scroll((0, self.scroll as u16))
+            .block(Block::bordered().title(title));
 
         frame.render_widget(input, area);
 
@@ -120,9 +266,9 @@ impl Input {
             // rendering
             #[allow(clippy::cast_possible_truncation)]
             InputMode::Editing => frame.set_cursor_position(Position::new(
-                // Draw the cursor at the current position in the input field.
-                // This position is can be controlled via the left and right arrow key
-                area.x + self.index as u16 + 1,
+                // Draw the cursor at the current position in the input field, minus the part
+                // scrolled out on the left
+                area.x + (self.cursor_column() - self.scroll) as u16 + 1,
                 // Move one line down, from the border to the input line
                 area.y + 1,
             )),
@@ -130,50 +276,148 @@ impl Input {
     }
 }
 
+// Entrée de menu: l'action renvoyée quand elle est choisie
+pub struct MenuItem<A> {
+    pub label: String,
+    pub action: A,
+    // Affichée en grisé, ni sélectionnable ni déclenchable
+    pub enabled: bool,
+    // Touche qui déclenche l'action directement
+    pub shortcut: Option<char>,
+}
+
+impl<A> MenuItem<A> {
+    pub fn new(label: impl Into<String>, action: A) -> Self {
+        Self {
+            label: label.into(),
+            action,
+            enabled: true,
+            shortcut: None,
+        }
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn shortcut(mut self, key: char) -> Self {
+        self.shortcut = Some(key);
+        self
+    }
+}
+
 // Menu
-pub struct Menu {
+pub struct Menu<A> {
     title: String,
-    options: Vec<String>,
+    items: Vec<MenuItem<A>>,
     pub state: ListState,
 }
 
-impl Menu {
-    pub fn new(title: &str, options: Vec<String>) -> Self {
-        let mut state = ListState::default();
+impl<A: Copy + PartialEq> Menu<A> {
+    pub fn new(title: &str, items: Vec<MenuItem<A>>) -> Self {
+        let mut menu = Self {
+            title: title.to_string(),
+            items,
+            state: ListState::default(),
+        };
+        menu.state.select(menu.first_enabled());
+        menu
+    }
+
+    // Remplace les entrées en gardant la sélection sur la même action, si elle est toujours là
+    pub fn set_items(&mut self, items: Vec<MenuItem<A>>) {
+        let selected = self.selected();
+        self.items = items;
+        let index = selected
+            .and_then(|action| {
+                self.items
+                    .iter()
+                    .position(|item| item.enabled && item.action == action)
+            })
+            .or_else(|| self.first_enabled());
+        self.state.select(index);
+    }
 
-        state.select(Some(0));
+    pub fn selected(&self) -> Option<A> {
+        let item = self.items.get(self.state.selected()?)?;
+        item.enabled.then_some(item.action)
+    }
 
-        return Self {
-            options,
-            state,
-            title: title.to_string(),
+    fn first_enabled(&self) -> Option<usize> {
+        self.items.iter().position(|item| item.enabled)
+    }
+
+    fn last_enabled(&self) -> Option<usize> {
+        self.items.iter().rposition(|item| item.enabled)
+    }
+
+    // Entrée active suivante (`forward`) ou précédente; la sélection reste en place au bout du menu
+    fn step(&mut self, forward: bool) {
+        let current = match self.state.selected() {
+            Some(index) => index,
+            None => return self.state.select(self.first_enabled()),
+        };
+        let next = match forward {
+            true => (current + 1..self.items.len()).find(|&index| self.items[index].enabled),
+            false => (0..current).rev().find(|&index| self.items[index].enabled),
         };
+        if next.is_some() {
+            self.state.select(next);
+        }
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) {
+    // Déplace la sélection, ou renvoie l'action choisie (Entrée ou raccourci)
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<A> {
+        // Ctrl-C ou Ctrl-D ne doivent pas déclencher « Se connecter » ou « Se déconnecter »
+        if let KeyCode::Char(_) = key.code {
+            if key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            {
+                return None;
+            }
+        }
+        if let KeyCode::Char(c) = key.code {
+            if let Some(item) = self
+                .items
+                .iter()
+                .find(|item| item.enabled && item.shortcut == Some(c))
+            {
+                return Some(item.action);
+            }
+        }
         match key.code {
+            KeyCode::Enter => return self.selected(),
             KeyCode::Char('h') | KeyCode::Left => self.state.select(None),
-            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
-            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
-            KeyCode::Char('g') | KeyCode::Home => self.state.select_first(),
-            KeyCode::Char('G') | KeyCode::End => self.state.select_last(),
+            KeyCode::Char('j') | KeyCode::Down => self.step(true),
+            KeyCode::Char('k') | KeyCode::Up => self.step(false),
+            KeyCode::Char('g') | KeyCode::Home => self.state.select(self.first_enabled()),
+            KeyCode::Char('G') | KeyCode::End => self.state.select(self.last_enabled()),
             _ => {}
         }
+        None
     }
 }
 
-impl Widget for &mut Menu {
+impl<A> Widget for &mut Menu<A> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let block = Block::new()
-            .title(Line::raw("Actions").centered().italic().bold())
+            .title(Line::raw(self.title.as_str()).centered().italic().bold())
             .borders(Borders::TOP)
             .border_set(symbols::border::EMPTY);
 
-        let list = List::new(
-            self.options
-                .iter()
-                .map(|item| ListItem::new(Line::from(Span::raw(format!("{item}"))))),
-        )
+        let list = List::new(self.items.iter().map(|item| {
+            let mut spans = vec![Span::raw(item.label.as_str())];
+            if let Some(key) = item.shortcut {
+                spans.push(Span::raw(format!(" [{}]", key)).dark_gray());
+            }
+            let line = Line::from(spans);
+            match item.enabled {
+                true => ListItem::new(line),
+                false => ListItem::new(line).dark_gray(),
+            }
+        }))
         .block(block)
         .style(Style::new().white())
         .highlight_style(Style::new().bold().yellow())
@@ -1,92 +1,841 @@
 use std::env;
-use std::fs::File;
-use std::io::{self, prelude::*, BufRead};
+use std::fmt;
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
+use crate::i18n::{fill, tr};
+use crate::keyring;
+use crate::vault::{self, VaultError};
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Version du format du fichier, indépendante de la version du programme: elle n'augmente que quand
+// la disposition change, et chaque ancienne disposition a son étape de migration (voir `MIGRATIONS`)
+const SCHEMA_VERSION: u32 = 3;
+
+const CONFIG_DIR_NAME: &str = ".internat-connection";
+const CONFIG_FILE_NAME: &str = "config.toml";
+// Emplacement utilisé jusqu'au schéma 2
+const LEGACY_CONFIG_FILE_NAME: &str = ".internat-connection.txt";
+// Phrase de passe du coffre, pour les lancements sans terminal (daemon, service)
+pub const PASSPHRASE_VARIABLE: &str = "INTERNAT_PASSPHRASE";
+
+// Écrit en tête d'un nouveau fichier; les commentaires sont conservés à chaque sauvegarde
+const TEMPLATE: &str = "\
+# Configuration de ConnectionInternat
+#
+# Réglages disponibles (retirez le # pour les activer):
+# ping_interval = 50                 # secondes entre deux pings
+# reconnect_max_delay = 300          # délai maximal entre deux reconnexions, en secondes
+# backend_path = \"/chemin/vers/ConnectionInternat-backend.exe\"
+# portal_url = \"https://controller.access.network/portal_api.php\"
+# language = \"fr\"                    # fr ou en
+# use_keyring = false                # garder le mot de passe dans ce fichier plutôt que dans le trousseau
+";
+
 pub struct Config {
-    pub update: bool,
+    pub path: PathBuf,
+    // Renseigné quand la configuration vient d'une version précédente du programme
+    pub update: Option<Update>,
     pub version: String,
     pub username: String,
+    // Mot de passe en clair, en mémoire seulement en mode coffre
     pub password: String,
+
+    // Mode coffre: le mot de passe est enregistré chiffré (`encrypted_password`). Tant que la phrase
+    // de passe n'est pas connue, la configuration est verrouillée et `password` est vide.
+    sealedPassword: Option<String>,
+    passphrase: Option<String>,
+
+    // Identifiant et mot de passe tels qu'enregistrés dans le trousseau du système; le fichier ne
+    // garde alors que `password_storage = "keyring"`
+    keyringEntry: Option<(String, String)>,
+    // Dernier problème avec le trousseau: le mot de passe est resté dans le fichier, ou n'a pas pu
+    // être lu
+    pub keyringError: Option<String>,
+
+    // Réglages; `None` garde la valeur par défaut du programme
+    pub pingInterval: Option<i64>,
+    pub reconnectMaxDelay: Option<u64>,
+    pub backendPath: Option<String>,
+    pub portalUrl: Option<String>,
+    pub autoConnect: bool,
+    pub language: Option<String>,
+    // `None`: trousseau utilisé s'il existe, sans signaler son absence
+    pub useKeyring: Option<bool>,
+
+    // Lignes du fichier lu: commentaires et clés inconnues sont réécrits tels quels
+    document: Vec<String>,
+}
+
+pub struct Update {
+    // Version du programme qui avait écrit la configuration
+    pub from: String,
+    // Description de chaque migration appliquée
+    pub changes: Vec<String>,
+}
+
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    Vault(VaultError),
+    // ancienne disposition qui ne correspond pas à ce qu'écrivait la version précédente
+    Layout {
+        path: PathBuf,
+        schema: u32,
+        message: String,
+    },
 }
 
-fn create_config(path: &Path) {
-    let mut file = File::create(path).expect("[ERREUR]: Impossible de créer la configuration.\n");
-    file.write_all(format!("{}\n", VERSION).as_bytes()).unwrap();
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ConfigError::Io { path, error } => fill(tr().configIoError, &[&path.display(), error]),
+            ConfigError::Syntax {
+                path,
+                line,
+                message,
+            } => fill(tr().configSyntaxError, &[&path.display(), line, message]),
+            ConfigError::Vault(error) => {
+                fill(tr().configVaultError, &[&PASSPHRASE_VARIABLE, error])
+            }
+            ConfigError::Layout {
+                path,
+                schema,
+                message,
+            } => fill(tr().configLayoutError, &[&path.display(), schema, message]),
+        };
+        write!(f, "{}", message)
+    }
 }
 
-fn get_config_path() -> PathBuf {
-    let home_dir = PathBuf::from(match env::var("HOME") {
+// Une étape de migration fait passer les lignes du fichier du schéma N au schéma N + 1, et décrit
+// ce qui a changé
+type Migration = fn(Vec<String>) -> (Vec<String>, &'static str);
+
+// `MIGRATIONS[n]` fait passer du schéma n + 1 au schéma n + 2
+const MIGRATIONS: [Migration; 2] = [migrate_v1_to_v2, migrate_v2_to_v3];
+
+// Schéma 1 (jusqu'à 0.0.2): version du programme, identifiant, mot de passe
+// Schéma 2: `schema=2`, version du programme, identifiant, mot de passe
+fn migrate_v1_to_v2(mut lines: Vec<String>) -> (Vec<String>, &'static str) {
+    lines.insert(0, "schema=2".to_string());
+    (lines, tr().migrationSchema)
+}
+
+// Schéma 3: une ligne `clé = valeur` par réglage
+fn migrate_v2_to_v3(lines: Vec<String>) -> (Vec<String>, &'static str) {
+    let line = |index: usize| lines.get(index).cloned().unwrap_or_default();
+    let mut migrated: Vec<String> = TEMPLATE.lines().map(String::from).collect();
+    migrated.push(String::new());
+    migrated.push("schema = 3".to_string());
+    migrated.push(format!("version = {}", quote(&line(1))));
+    migrated.push(format!("username = {}", quote(&line(2))));
+    migrated.push(format!("password = {}", quote(&line(3))));
+    (migrated, tr().migrationKeyValue)
+}
+
+// Avant toute migration, le fichier doit avoir exactement la disposition écrite par l'ancienne
+// version: les migrations se fient à la position des lignes, et une erreur de lecture ferait perdre
+// les identifiants
+fn check_legacy_layout(schema: u32, lines: &[String]) -> Result<(), String> {
+    let mut lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    if schema == 2 {
+        if lines.first().map(|line| line.trim()) != Some("schema=2") {
+            return Err(tr().layoutSchemaHeader.to_string());
+        }
+        lines.remove(0);
+    }
+    // version du programme, puis identifiant et mot de passe s'ils ont été sauvegardés
+    if lines.is_empty() || lines.len() > 3 {
+        return Err(fill(tr().layoutLineCount, &[&lines.len()]));
+    }
+    let version = lines[0].trim();
+    let looksLikeVersion = version.chars().any(|c| c.is_ascii_digit())
+        && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+    if !looksLikeVersion {
+        return Err(fill(tr().layoutVersion, &[&version]));
+    }
+    Ok(())
+}
+
+// Copie du fichier avant sa réécriture au nouveau format: `<fichier>.schema<N>.bak`
+fn backup(path: &Path, schema: u32) -> io::Result<PathBuf> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".schema{}.bak", schema));
+    let backup = PathBuf::from(name);
+    fs::copy(path, &backup)?;
+    restrict_permissions(&backup)?;
+    Ok(backup)
+}
+
+fn get_home_dir() -> PathBuf {
+    PathBuf::from(match env::var("HOME") {
         Ok(path) => path,
         Err(_) => match env::var("USERPROFILE") {
             Ok(path) => path,
             Err(_) => panic!("Home directory not found"),
         },
-    });
-    return home_dir.join(".internat-connection.txt");
+    })
+}
+
+// Dossier du programme: configuration, et tout ce qui doit survivre à un redémarrage
+pub fn get_config_dir() -> PathBuf {
+    get_home_dir().join(CONFIG_DIR_NAME)
+}
+
+pub fn get_config_path() -> PathBuf {
+    get_config_dir().join(CONFIG_FILE_NAME)
+}
+
+// Langue réglée dans le fichier, lue avant le chargement pour que ses messages (migration, erreurs,
+// trousseau) soient déjà dans cette langue; `None` sans réglage ou sans fichier lisible
+pub fn configured_language(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    content.lines().find_map(|line| match parse_line(line) {
+        Ok(Some((key, Value::String(language)))) if key == "language" => Some(language),
+        _ => None,
+    })
+}
+
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl Value {
+    fn describe(&self) -> &'static str {
+        match self {
+            Value::String(_) => tr().kindText,
+            Value::Integer(_) => tr().kindNumber,
+            Value::Boolean(_) => tr().kindBoolean,
+        }
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Sous-ensemble de TOML: `clé = valeur` sur une ligne, avec des textes entre guillemets, des nombres
+// entiers et des booléens; `#` commence un commentaire. Renvoie `None` pour une ligne sans réglage.
+fn parse_line(line: &str) -> Result<Option<(String, Value)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    if line.starts_with('[') {
+        return Err(tr().syntaxSection.to_string());
+    }
+
+    let (key, rest) = line
+        .split_once('=')
+        .ok_or_else(|| fill(tr().syntaxKeyValue, &[&line]))?;
+    let key = key.trim();
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(fill(tr().syntaxKeyName, &[&key]));
+    }
+
+    let rest = rest.trim();
+    let (value, after) = match rest.chars().next() {
+        Some(delimiter @ ('"' | '\'')) => parse_string(&rest[1..], delimiter)?,
+        _ => {
+            let raw = rest.split('#').next().unwrap_or_default().trim();
+            let value = match raw {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                "" => return Err(fill(tr().syntaxMissingValue, &[&key])),
+                _ => match raw.replace('_', "").parse::<i64>() {
+                    Ok(number) => Value::Integer(number),
+                    Err(_) => return Err(fill(tr().syntaxInvalidValue, &[&key, &raw])),
+                },
+            };
+            (value, "")
+        }
+    };
+
+    let after = after.trim();
+    if !after.is_empty() && !after.starts_with('#') {
+        return Err(fill(tr().syntaxTrailing, &[&after]));
+    }
+    Ok(Some((key.to_string(), value)))
+}
+
+// Lit un texte après son guillemet ouvrant; renvoie la valeur et la suite de la ligne
+fn parse_string(input: &str, delimiter: char) -> Result<(Value, &str), String> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c == delimiter => return Ok((Value::String(value), &input[index + 1..])),
+            // pas d'échappement entre apostrophes, comme en TOML
+            '\\' if delimiter == '"' => match chars.next() {
+                Some((_, '"')) => value.push('"'),
+                Some((_, '\\')) => value.push('\\'),
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, other)) => return Err(fill(tr().syntaxEscape, &[&other])),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err(fill(tr().syntaxQuote, &[&delimiter]))
+}
+
+// Le schéma 1 n'avait pas d'en-tête: sa première ligne est la version du programme, sans `=`.
+// Sinon la clé `schema` donne le format, où qu'elle soit; un fichier `clé = valeur` sans cette clé
+// (ou avec une valeur illisible) est lu au format actuel, mais ce format est alors deviné.
+// Renvoie le schéma, et s'il a été deviné.
+fn schema_of(lines: &[String]) -> (u32, bool) {
+    let mut settings = lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    match settings.next() {
+        Some(first) if !first.contains('=') && !first.starts_with('[') => return (1, false),
+        None => return (SCHEMA_VERSION, true),
+        Some(_) => {}
+    }
+    for line in lines {
+        if let Ok(Some((key, value))) = parse_line(line) {
+            if key == "schema" {
+                return match value {
+                    Value::Integer(schema) if schema > 0 => (schema as u32, false),
+                    _ => (SCHEMA_VERSION, true),
+                };
+            }
+        }
+    }
+    (SCHEMA_VERSION, true)
 }
 
 impl Config {
-    pub fn init() -> Self {
-        let mut create = false;
-        let mut update = false;
-        let mut username = String::new();
-        let mut password = String::new();
-
-        let config_path = get_config_path();
-
-        if config_path.exists() {
-            let file =
-                File::open(&config_path).expect("[ERREUR]: Impossible de lire la configuration.\n");
-            let mut index = 0;
-
-            for line in io::BufReader::new(file).lines().flatten() {
-                match index {
-                    0 => {
-                        if line != VERSION {
-                            update = true;
-                            create_config(&config_path);
-                            break;
-                        }
-                    }
-                    1 => {
-                        username = line;
-                    }
-                    2 => {
-                        password = line;
-                    }
-                    _ => {
-                        break;
+    // Configuration par défaut; à l'ancien emplacement, elle est déplacée dans le dossier du programme
+    pub fn init() -> Result<Self, ConfigError> {
+        let path = get_config_path();
+        let legacy = get_home_dir().join(LEGACY_CONFIG_FILE_NAME);
+        if path.exists() || !legacy.exists() {
+            return Self::load(path);
+        }
+
+        let mut config = Self::load(legacy.clone())?;
+        config.path = path;
+        config.save()?;
+        fs::remove_file(&legacy).map_err(|error| ConfigError::Io {
+            path: legacy.clone(),
+            error,
+        })?;
+
+        let change = fill(
+            tr().configMoved,
+            &[&legacy.display(), &config.path.display()],
+        );
+        match &mut config.update {
+            Some(update) => update.changes.push(change),
+            None => {
+                config.update = Some(Update {
+                    from: VERSION.to_string(),
+                    changes: vec![change],
+                })
+            }
+        }
+        Ok(config)
+    }
+
+    // Comme `init`, sans rien écrire: ni création, ni migration, ni déplacement (commande `status`).
+    // La migration éventuelle reste à faire, et sera signalée au prochain lancement.
+    pub fn read() -> Result<Self, ConfigError> {
+        let path = get_config_path();
+        let legacy = get_home_dir().join(LEGACY_CONFIG_FILE_NAME);
+        if path.exists() || !legacy.exists() {
+            return Self::read_from(path);
+        }
+        // lue à l'ancien emplacement, mais le dossier du programme reste celui des autres fichiers
+        let mut config = Self::read_from(legacy)?;
+        config.path = path;
+        Ok(config)
+    }
+
+    pub fn read_from(config_path: PathBuf) -> Result<Self, ConfigError> {
+        Self::open(config_path, false)
+    }
+
+    pub fn load(config_path: PathBuf) -> Result<Self, ConfigError> {
+        Self::open(config_path, true)
+    }
+
+    // `write`: crée le fichier absent et enregistre la migration
+    fn open(config_path: PathBuf, write: bool) -> Result<Self, ConfigError> {
+        let io_error = |error| ConfigError::Io {
+            path: config_path.clone(),
+            error,
+        };
+
+        let mut config = Self {
+            path: config_path.clone(),
+            update: None,
+            version: VERSION.to_string(),
+            username: String::new(),
+            password: String::new(),
+            sealedPassword: None,
+            passphrase: None,
+            keyringEntry: None,
+            keyringError: None,
+            pingInterval: None,
+            reconnectMaxDelay: None,
+            backendPath: None,
+            portalUrl: None,
+            autoConnect: false,
+            language: None,
+            useKeyring: None,
+            document: TEMPLATE.lines().map(String::from).collect(),
+        };
+
+        if !config_path.exists() {
+            if write {
+                config.save()?;
+            }
+            return Ok(config);
+        }
+
+        let content = fs::read_to_string(&config_path).map_err(io_error)?;
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
+        if lines.iter().all(|line| line.trim().is_empty()) {
+            // fichier vide: rien à migrer
+            if write {
+                config.save()?;
+            }
+            return Ok(config);
+        }
+
+        let (schema, guessed) = schema_of(&lines);
+        if schema < SCHEMA_VERSION {
+            check_legacy_layout(schema, &lines).map_err(|message| ConfigError::Layout {
+                path: config_path.clone(),
+                schema,
+                message,
+            })?;
+        }
+        let mut changes = Vec::new();
+        if schema > SCHEMA_VERSION {
+            changes.push(fill(tr().configNewer, &[&schema, &SCHEMA_VERSION]));
+        } else {
+            for migration in &MIGRATIONS[(schema - 1) as usize..] {
+                let (migrated, change) = migration(lines);
+                lines = migrated;
+                changes.push(change.to_string());
+            }
+        }
+
+        let mut previous = String::new();
+        let mut storage = String::new();
+        let mut seen: Vec<(String, usize)> = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let number = index + 1;
+            let syntax = |message: String| ConfigError::Syntax {
+                path: config_path.clone(),
+                line: number,
+                message,
+            };
+
+            let (key, value) = match parse_line(line).map_err(syntax)? {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if let Some((_, first)) = seen.iter().find(|(seen, _)| *seen == key) {
+                return Err(syntax(fill(tr().syntaxDuplicate, &[&key, first])));
+            }
+            seen.push((key.clone(), number));
+
+            let expected = |expected: &str| {
+                syntax(match value {
+                    Value::Integer(_) if expected == tr().kindPositiveNumber => {
+                        fill(tr().syntaxExpected, &[&key, &expected])
                     }
+                    _ => fill(
+                        tr().syntaxExpectedFound,
+                        &[&key, &expected, &value.describe()],
+                    ),
+                })
+            };
+            match (key.as_str(), &value) {
+                ("schema", _) => {}
+                ("version", Value::String(version)) => previous = version.clone(),
+                ("username", Value::String(username)) => config.username = username.clone(),
+                ("password", Value::String(password)) => config.password = password.clone(),
+                ("password_storage", Value::String(name)) => storage = name.clone(),
+                ("encrypted_password", Value::String(sealed)) => {
+                    config.sealedPassword = Some(sealed.clone())
+                }
+                ("ping_interval", Value::Integer(seconds)) if *seconds > 0 => {
+                    config.pingInterval = Some(*seconds)
                 }
-                index += 1;
+                ("reconnect_max_delay", Value::Integer(seconds)) if *seconds > 0 => {
+                    config.reconnectMaxDelay = Some(*seconds as u64)
+                }
+                ("backend_path", Value::String(path)) => config.backendPath = Some(path.clone()),
+                ("portal_url", Value::String(url)) => config.portalUrl = Some(url.clone()),
+                ("auto_connect", Value::Boolean(enabled)) => config.autoConnect = *enabled,
+                ("language", Value::String(language)) => config.language = Some(language.clone()),
+                ("use_keyring", Value::Boolean(enabled)) => config.useKeyring = Some(*enabled),
+                (
+                    "version" | "username" | "password" | "encrypted_password" | "backend_path"
+                    | "portal_url" | "language" | "password_storage",
+                    _,
+                ) => return Err(expected(tr().kindQuotedText)),
+                ("ping_interval" | "reconnect_max_delay", _) => {
+                    return Err(expected(tr().kindPositiveNumber))
+                }
+                ("auto_connect" | "use_keyring", _) => return Err(expected(tr().kindBoolean)),
+                // clé inconnue (version plus récente, ou ajout à la main): gardée telle quelle
+                _ => {}
             }
-        } else {
-            create_config(&config_path);
         }
+        config.document = lines;
 
-        Self {
-            update,
-            username,
-            password,
-            version: VERSION.to_string(),
+        if config.sealedPassword.is_some() {
+            // mode coffre: le mot de passe en clair ne doit plus être lu
+            config.password = String::new();
+            if let Ok(passphrase) = env::var(PASSPHRASE_VARIABLE) {
+                config.unlock(&passphrase).map_err(ConfigError::Vault)?;
+            }
+        } else if storage == "keyring" {
+            config.load_from_keyring();
+        }
+
+        // le schéma 1 n'avait pas de clé `version`: c'était la première ligne du fichier
+        if schema == 1 {
+            previous = content.lines().next().unwrap_or_default().to_string();
+        }
+        if !changes.is_empty() || (!previous.is_empty() && previous != VERSION) {
+            config.update = Some(Update {
+                from: previous,
+                changes,
+            });
         }
+        // la migration est écrite tout de suite, pour ne pas la refaire au prochain lancement; le
+        // fichier d'origine est gardé à côté. Un format deviné n'est jamais réécrit.
+        if write && !guessed && config.update.is_some() && schema <= SCHEMA_VERSION {
+            if schema < SCHEMA_VERSION {
+                let backup = backup(&config_path, schema).map_err(io_error)?;
+                if let Some(update) = &mut config.update {
+                    update
+                        .changes
+                        .push(fill(tr().configBackedUp, &[&backup.display()]));
+                }
+            }
+            config.save()?;
+        }
+        Ok(config)
     }
 
-    pub fn save(&self) {
-        let config_path = get_config_path();
+    // Dossier du fichier de configuration utilisé: la session et les rapports de plantage y vont aussi
+    pub fn dir(&self) -> PathBuf {
+        self.path
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default()
+    }
 
-        if config_path.exists() {
-            let mut file = File::create(&config_path)
-                .expect("[ERREUR]: Impossible de sauvegarder la configuration - 0.\n");
-            file.write_all(
-                format!("{}\n{}\n{}\n", VERSION, self.username, self.password).as_bytes(),
-            )
-            .expect("[ERREUR]: Impossible de sauvegarder la configuration - 1.\n");
-        } else {
-            create_config(&config_path);
+    pub fn is_vault(&self) -> bool {
+        self.sealedPassword.is_some() || self.passphrase.is_some()
+    }
+
+    // Le mot de passe est chiffré et la phrase de passe n'a pas encore été donnée
+    pub fn is_locked(&self) -> bool {
+        self.sealedPassword.is_some() && self.passphrase.is_none()
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), VaultError> {
+        if let Some(sealed) = &self.sealedPassword {
+            self.password = vault::open(sealed, passphrase)?;
+        }
+        self.passphrase = Some(passphrase.to_string());
+        Ok(())
+    }
+
+    // Active le mode coffre avec cette phrase de passe, ou le désactive avec `None`; le mot de passe
+    // est rechiffré à la prochaine sauvegarde
+    pub fn set_passphrase(&mut self, passphrase: Option<String>) {
+        self.passphrase = passphrase;
+        self.sealedPassword = None;
+    }
+
+    // Oublier les identifiants désactive aussi le coffre, qui n'a plus rien à protéger, et retire
+    // l'entrée du trousseau
+    pub fn forget_credentials(&mut self) {
+        if let Some((username, _)) = self.keyringEntry.take() {
+            self.keyringError = keyring::delete(&username).err();
+        }
+        self.username = String::new();
+        self.password = String::new();
+        self.set_passphrase(None);
+    }
+
+    // Le mot de passe est dans le trousseau (ou y est resté faute de pouvoir le lire)
+    pub fn in_keyring(&self) -> bool {
+        self.keyringEntry.is_some()
+    }
+
+    fn load_from_keyring(&mut self) {
+        match keyring::load(&self.username) {
+            Ok(Some(password)) => {
+                self.keyringEntry = Some((self.username.clone(), password.clone()));
+                self.password = password;
+            }
+            Ok(None) => self.keyringError = Some(tr().keyringPasswordMissing.to_string()),
+            // trousseau verrouillé ou absent: l'entrée est gardée pour un prochain lancement
+            Err(error) => {
+                self.keyringEntry = Some((self.username.clone(), String::new()));
+                self.keyringError = Some(error);
+            }
         }
     }
+
+    // Met le trousseau en accord avec les identifiants actuels. En mode coffre, ou si le trousseau
+    // est désactivé ou indisponible, le mot de passe va dans le fichier.
+    fn sync_keyring(&mut self) {
+        let useKeyring = self.useKeyring.unwrap_or(true);
+        let wanted =
+            keyring::SUPPORTED && useKeyring && !self.is_vault() && !self.password.is_empty();
+        if let Some((username, password)) = &self.keyringEntry {
+            let unchanged = *username == self.username && *password == self.password;
+            if unchanged && useKeyring && !self.is_vault() {
+                return;
+            }
+            if *username != self.username || !wanted {
+                self.keyringError = keyring::delete(username).err();
+                self.keyringEntry = None;
+            }
+        }
+        if !wanted {
+            return;
+        }
+        match keyring::store(&self.username, &self.password) {
+            Ok(()) => {
+                self.keyringEntry = Some((self.username.clone(), self.password.clone()));
+                self.keyringError = None;
+            }
+            // une machine sans bureau n'a souvent pas de trousseau: l'absence n'est signalée que si
+            // l'utilisateur l'a demandé avec `use_keyring = true`
+            Err(error) => {
+                self.keyringEntry = None;
+                self.keyringError = match self.useKeyring {
+                    Some(true) => Some(fill(tr().keyringKeptInFile, &[&error])),
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    // Valeur actuelle des réglages connus, dans l'ordre où ils sont ajoutés à un nouveau fichier
+    fn entries(&self) -> Vec<(&'static str, Option<String>)> {
+        let (password, sealed) = match (&self.passphrase, &self.sealedPassword) {
+            (Some(passphrase), _) => (None, Some(vault::seal(&self.password, passphrase))),
+            // verrouillé: le mot de passe chiffré est réécrit tel quel
+            (None, Some(sealed)) => (None, Some(sealed.clone())),
+            (None, None) => (Some(self.password.clone()), None),
+        };
+        let in_keyring = password.is_some()
+            && self.keyringEntry.as_ref() == Some(&(self.username.clone(), self.password.clone()));
+        let (password, storage) = match in_keyring {
+            true => (None, Some(quote("keyring"))),
+            false => (password, None),
+        };
+        vec![
+            ("schema", Some(SCHEMA_VERSION.to_string())),
+            ("version", Some(quote(VERSION))),
+            ("username", Some(quote(&self.username))),
+            ("password", password.as_deref().map(quote)),
+            ("password_storage", storage),
+            ("encrypted_password", sealed.as_deref().map(quote)),
+            ("ping_interval", self.pingInterval.map(|s| s.to_string())),
+            (
+                "reconnect_max_delay",
+                self.reconnectMaxDelay.map(|s| s.to_string()),
+            ),
+            ("backend_path", self.backendPath.as_deref().map(quote)),
+            ("portal_url", self.portalUrl.as_deref().map(quote)),
+            ("auto_connect", Some(self.autoConnect.to_string())),
+            ("language", self.language.as_deref().map(quote)),
+            (
+                "use_keyring",
+                self.useKeyring.map(|enabled| enabled.to_string()),
+            ),
+        ]
+    }
+
+    pub fn save(&mut self) -> Result<(), ConfigError> {
+        self.sync_keyring();
+        let io_error = |error| ConfigError::Io {
+            path: self.path.clone(),
+            error,
+        };
+
+        // chaque réglage connu remplace sa ligne; le reste du fichier ne change pas
+        let mut entries = self.entries();
+        let mut content = String::new();
+        for line in &self.document {
+            if let Ok(Some((key, _))) = parse_line(line) {
+                if let Some(position) = entries.iter().position(|(known, _)| *known == key) {
+                    if let (known, Some(value)) = entries.remove(position) {
+                        content.push_str(&format!("{} = {}\n", known, value));
+                    }
+                    continue;
+                }
+            }
+            content.push_str(line);
+            content.push('\n');
+        }
+        for (key, value) in entries {
+            if let Some(value) = value {
+                content.push_str(&format!("{} = {}\n", key, value));
+            }
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        fs::write(&self.path, content).map_err(io_error)?;
+        restrict_permissions(&self.path).map_err(io_error)
+    }
+}
+
+// Le fichier contient le mot de passe: lisible par son seul propriétaire
+#[cfg(unix)]
+pub fn restrict_permissions(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+pub fn restrict_permissions(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fichier de configuration dans un dossier temporaire propre au test
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "connection-internat-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn load(path: &Path) -> Config {
+        Config::load(path.to_path_buf()).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    #[test]
+    fn keys_in_any_order_are_not_migrated() {
+        let content = format!(
+            "username = \"alice\"\npassword = \"secret\"\nschema = 3\nversion = \"{}\"\n",
+            VERSION
+        );
+        let path = write_config("order", &content);
+        let config = load(&path);
+        assert_eq!(config.username, "alice");
+        assert_eq!(config.password, "secret");
+        assert!(config.update.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn missing_schema_key_is_never_rewritten() {
+        let content =
+            "# réglages\nusername = \"alice\"\npassword = \"secret\"\nversion = \"0.0.1\"\n";
+        let path = write_config("guessed", content);
+        let config = load(&path);
+        assert_eq!(config.username, "alice");
+        assert_eq!(config.password, "secret");
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn schema_1_is_migrated_and_backed_up() {
+        // sans mot de passe, l'enregistrement ne passe pas par le trousseau
+        let content = "0.0.1\nalice\n";
+        let path = write_config("v1", content);
+        let config = load(&path);
+        assert_eq!(config.username, "alice");
+        assert_eq!(
+            config.update.map(|update| update.from).as_deref(),
+            Some("0.0.1")
+        );
+        assert_eq!(
+            schema_of(
+                &fs::read_to_string(&path)
+                    .unwrap()
+                    .lines()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            ),
+            (SCHEMA_VERSION, false)
+        );
+        assert_eq!(
+            fs::read_to_string(path.with_extension("toml.schema1.bak")).unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn unexpected_legacy_layout_is_refused() {
+        let content = "0.0.1\nalice\nsecret\nautre chose\n";
+        let path = write_config("layout", content);
+        assert!(matches!(
+            Config::load(path.clone()),
+            Err(ConfigError::Layout { schema: 1, .. })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn language_is_read_before_loading() {
+        let path = write_config(
+            "language",
+            "schema = 3\n# langue\nlanguage = 'en' # anglais\n",
+        );
+        assert_eq!(configured_language(&path).as_deref(), Some("en"));
+        let path = write_config("no-language", "schema = 3\nlanguage = 2\n");
+        assert_eq!(configured_language(&path), None);
+    }
 }
@@ -0,0 +1,821 @@
+use chrono::{DateTime, Local};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::backend::{BackendHandle, BackendStatus, JobResult, PortalBackend};
+use crate::backoff::Backoff;
+use crate::crash;
+use crate::eventlog::EventLog;
+use crate::i18n::{fill, tr};
+use crate::protocol::{Action, ErrorCode, Params, Payload, ResponseError};
+use crate::session::{self, PendingLogout, SavedSession};
+use crate::{DATE_FORMAT, PING_INTERVAL, RECONNECT_BASE_DELAY};
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ConnectionStatus {
+    Uninitialized,
+    Connected,
+    Disconnected,
+    Connecting,
+}
+
+impl ConnectionStatus {
+    // pour le socket de contrôle, qui n'existe que sous Unix
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionStatus::Uninitialized => "uninitialized",
+            ConnectionStatus::Connected => "connected",
+            ConnectionStatus::Disconnected => "disconnected",
+            ConnectionStatus::Connecting => "connecting",
+        }
+    }
+}
+
+// Ce qui s'est passé depuis le dernier appel à `Connection::poll`; l'interface et le mode daemon
+// s'en servent pour changer d'écran ou écrire dans le journal
+pub enum ConnectionEvent {
+    LoggedIn,
+    LoginFailed(ResponseError),
+    // session du lancement précédent reprise sans nouvelle connexion
+    Resumed,
+    ResumeFailed(ResponseError),
+    // avec la durée du ping
+    PingSucceeded(Duration),
+    PingFailed(ResponseError, Duration),
+    ReconnectScheduled { attempt: u32, delay: Duration },
+    Reconnecting { attempt: u32 },
+    LoggedOut(Result<(), ResponseError>),
+    // nouvel essai de fermeture d'une session restée ouverte (identifiant, résultat)
+    DeferredLogout(String, Result<(), ResponseError>),
+    // session ou déconnexions en attente impossibles à écrire (message), signalé une fois
+    StateNotSaved(String),
+    // journal des événements impossible à écrire ou à renommer (message), signalé une fois
+    EventLogFailed(String),
+}
+
+impl ConnectionEvent {
+    // Nom stable, pour le journal des événements
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnectionEvent::LoggedIn => "logged_in",
+            ConnectionEvent::LoginFailed(_) => "login_failed",
+            ConnectionEvent::Resumed => "resumed",
+            ConnectionEvent::ResumeFailed(_) => "resume_failed",
+            ConnectionEvent::PingSucceeded(_) => "ping_succeeded",
+            ConnectionEvent::PingFailed(..) => "ping_failed",
+            ConnectionEvent::ReconnectScheduled { .. } => "reconnect_scheduled",
+            ConnectionEvent::Reconnecting { .. } => "reconnecting",
+            ConnectionEvent::LoggedOut(Ok(())) => "logged_out",
+            ConnectionEvent::LoggedOut(Err(_)) => "logout_failed",
+            ConnectionEvent::DeferredLogout(_, Ok(())) => "deferred_logout",
+            ConnectionEvent::DeferredLogout(_, Err(_)) => "deferred_logout_failed",
+            ConnectionEvent::StateNotSaved(_) => "state_not_saved",
+            ConnectionEvent::EventLogFailed(_) => "event_log_failed",
+        }
+    }
+
+    pub fn error(&self) -> Option<&ResponseError> {
+        match self {
+            ConnectionEvent::LoginFailed(err)
+            | ConnectionEvent::ResumeFailed(err)
+            | ConnectionEvent::PingFailed(err, _)
+            | ConnectionEvent::LoggedOut(Err(err))
+            | ConnectionEvent::DeferredLogout(_, Err(err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    // Description courte, pour le rapport de plantage
+    pub fn describe(&self) -> String {
+        match (self, self.error()) {
+            (ConnectionEvent::ReconnectScheduled { attempt, delay }, _) => format!(
+                "{} (attempt {}, {}s)",
+                self.name(),
+                attempt,
+                delay.as_secs()
+            ),
+            (ConnectionEvent::Reconnecting { attempt }, _) => {
+                format!("{} (attempt {})", self.name(), attempt)
+            }
+            (_, Some(err)) => format!("{} ({})", self.name(), err.code),
+            (_, None) => self.name().to_string(),
+        }
+    }
+}
+
+// État de la session sur le contrôleur: connexion, ping régulier, reconnexion et déconnexion.
+// Partagé entre l'interface (`App`) et le mode daemon, qui n'en font que l'affichage.
+pub struct Connection {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub passwordDigest: Option<String>,
+    pub status: ConnectionStatus,
+    pub lastLogin: Option<String>,
+    pub lastPingAttempt: Option<DateTime<Local>>,
+    pub lastPingTimestamp: Option<DateTime<Local>>,
+    pub lastError: Option<ResponseError>,
+    // dernière écriture de la session ou des déconnexions en attente qui a échoué
+    pub stateError: Option<String>,
+    // dernière écriture du journal des événements qui a échoué
+    pub eventLogError: Option<String>,
+    pub connectingSince: Option<Instant>,
+    // Intervalle entre deux pings, en secondes
+    pub pingInterval: i64,
+
+    // Reconnexion automatique après un échec
+    pub backoff: Backoff,
+
+    // Dossier où garder la session et les déconnexions en échec d'un lancement à l'autre; `None`
+    // pour ne rien écrire
+    stateDir: Option<PathBuf>,
+    // le ping en cours vérifie une session sauvegardée
+    resuming: bool,
+    // session en cours de fermeture, à garder pour plus tard si la déconnexion échoue
+    closing: Option<PendingLogout>,
+    // Sessions restées ouvertes, à fermer dès que le contrôleur répond
+    pub pendingLogouts: Vec<PendingLogout>,
+    // déconnexions retentées en arrière-plan, à côté des requêtes de la session courante
+    logoutJobs: Vec<(u64, String)>,
+    // journal des événements, dans le même dossier
+    eventLog: Option<EventLog>,
+
+    // Appels au backend en arrière-plan
+    backend: BackendHandle,
+    pendingJob: Option<u64>,
+    cancelledJobs: Vec<u64>,
+
+    events: Vec<ConnectionEvent>,
+}
+
+impl Connection {
+    pub fn new(backend: Box<dyn PortalBackend>, reconnectMaxDelay: Duration) -> Self {
+        Self {
+            username: None,
+            password: None,
+            passwordDigest: None,
+            status: ConnectionStatus::Uninitialized,
+            lastLogin: None,
+            lastPingAttempt: None,
+            lastPingTimestamp: None,
+            lastError: None,
+            stateError: None,
+            eventLogError: None,
+            connectingSince: None,
+            pingInterval: PING_INTERVAL,
+
+            backoff: Backoff::new(Duration::from_secs(RECONNECT_BASE_DELAY), reconnectMaxDelay),
+
+            stateDir: None,
+            resuming: false,
+            closing: None,
+            pendingLogouts: Vec::new(),
+            logoutJobs: Vec::new(),
+            eventLog: None,
+
+            backend: BackendHandle::spawn(backend),
+            pendingJob: None,
+            cancelledJobs: Vec::new(),
+
+            events: Vec::new(),
+        }
+    }
+
+    // Garde la session, les déconnexions en échec et le journal des événements dans ce dossier, et
+    // reprend les déconnexions en attente
+    pub fn keep_state_in(&mut self, dir: PathBuf) {
+        self.pendingLogouts = session::load_pending(&session::pending_path(&dir));
+        self.eventLog = Some(EventLog::new(&dir));
+        self.stateDir = Some(dir);
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.pendingJob.is_some()
+    }
+
+    // À appeler environ une fois par seconde: ping régulier et reconnexion automatique
+    pub fn tick(&mut self) {
+        if self.pendingJob.is_some() {
+            return;
+        }
+
+        match self.status {
+            ConnectionStatus::Connected if self.passwordDigest.is_some() => {
+                let seconds = seconds_since(self.lastPingAttempt).unwrap_or(0);
+                if seconds >= self.pingInterval {
+                    self.ping()
+                }
+            }
+            ConnectionStatus::Disconnected if self.backoff.is_due() => {
+                self.events.push(ConnectionEvent::Reconnecting {
+                    attempt: self.backoff.attempt(),
+                });
+                self.reconnect();
+            }
+            _ => {}
+        }
+    }
+
+    // État du backend, pour l'écran de statut
+    pub fn backend_status(&self) -> Option<BackendStatus> {
+        self.backend.status()
+    }
+
+    // Traite les réponses du backend reçues depuis le dernier appel
+    pub fn poll(&mut self) -> Vec<ConnectionEvent> {
+        while let Some(result) = self.backend.poll() {
+            self.handle_backend_result(result);
+        }
+        // en cas de plantage, la session sera fermée et ces événements dans le rapport
+        crash::remember_session(self.username.clone(), self.passwordDigest.clone());
+        let mut logged = Ok(());
+        for event in &self.events {
+            crash::record(event);
+            if let Some(eventLog) = &self.eventLog {
+                logged = logged.and(
+                    eventLog
+                        .record(event, self.username.clone())
+                        .map_err(|err| {
+                            fill(tr().eventLogFailed, &[&eventLog.path().display(), &err])
+                        }),
+                );
+            }
+        }
+        let mut events = std::mem::take(&mut self.events);
+        // l'échec n'est pas écrit dans le journal lui-même, et n'est signalé qu'une fois
+        match logged {
+            Ok(()) if !events.is_empty() => self.eventLogError = None,
+            Ok(()) => {}
+            Err(message) => {
+                if self.eventLogError.as_ref() != Some(&message) {
+                    events.push(ConnectionEvent::EventLogFailed(message.clone()));
+                }
+                self.eventLogError = Some(message);
+            }
+        }
+        events
+    }
+
+    // Attend la fin de la requête en cours (au plus `timeout`), pour les modes sans interface
+    pub fn wait(&mut self, timeout: Duration) -> Vec<ConnectionEvent> {
+        let deadline = Instant::now() + timeout;
+        let mut events = self.poll();
+        while self.is_busy() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+            events.extend(self.poll());
+        }
+        events
+    }
+
+    fn submit(&mut self, action: Action, params: Params) {
+        let id = self.backend.submit(action, params);
+        self.pendingJob = Some(id);
+    }
+
+    // Abandonne la requête en cours: sa réponse sera ignorée
+    fn forget_pending(&mut self) {
+        if let Some(id) = self.pendingJob.take() {
+            self.cancelledJobs.push(id);
+        }
+        self.closing = None;
+    }
+
+    fn handle_backend_result(&mut self, result: JobResult) {
+        if let Some(position) = self.logoutJobs.iter().position(|(id, _)| *id == result.id) {
+            let (_, digest) = self.logoutJobs.remove(position);
+            self.on_deferred_logout_result(digest, result.result);
+            return;
+        }
+
+        if let Some(position) = self.cancelledJobs.iter().position(|id| *id == result.id) {
+            self.cancelledJobs.remove(position);
+            // une connexion annulée qui a finalement réussi: on ferme la session aussitôt
+            if let (Action::Login, Ok(payload)) = (result.action, result.result) {
+                if let (Some(username), Some(digest)) = (payload.username, payload.digest) {
+                    self.defer_logout(PendingLogout {
+                        username,
+                        digest,
+                        since: Local::now().format(DATE_FORMAT).to_string(),
+                    });
+                    self.retry_logouts();
+                }
+            }
+            return;
+        }
+
+        if self.pendingJob != Some(result.id) {
+            return;
+        }
+        self.pendingJob = None;
+
+        match result.action {
+            Action::Login => self.on_login_result(result.result),
+            Action::Ping => self.on_ping_result(result.result, result.duration),
+            Action::Logout => self.on_logout_result(result.result),
+        }
+    }
+
+    // Session sauvegardée au lancement précédent pour cet identifiant
+    pub fn saved_session(&self, username: &str) -> Option<SavedSession> {
+        let saved = session::load(&session::path(self.stateDir.as_ref()?))?;
+        (saved.username == username).then_some(saved)
+    }
+
+    // Reprend la session sauvegardée de cet identifiant si elle répond encore à un ping, sinon ouvre
+    // une nouvelle session (avec le mot de passe, s'il est connu)
+    pub fn open(&mut self, username: String, password: Option<String>) {
+        match (self.saved_session(&username), password) {
+            (Some(saved), password) => {
+                self.restore(saved);
+                self.password = password;
+                self.resuming = true;
+                self.status = ConnectionStatus::Connecting;
+                self.connectingSince = Some(Instant::now());
+                self.ping();
+            }
+            (None, Some(password)) => self.login(username, password),
+            (None, None) => {}
+        }
+    }
+
+    // Reprend une session sauvegardée telle quelle, sans la vérifier
+    pub fn restore(&mut self, saved: SavedSession) {
+        self.username = Some(saved.username);
+        self.passwordDigest = Some(saved.digest);
+        self.lastLogin = Some(saved.lastLogin);
+    }
+
+    fn save_session(&mut self) {
+        let (Some(dir), Some(username), Some(digest), Some(lastLogin)) = (
+            &self.stateDir,
+            &self.username,
+            &self.passwordDigest,
+            &self.lastLogin,
+        ) else {
+            return;
+        };
+        let saved = SavedSession {
+            username: username.clone(),
+            digest: digest.clone(),
+            lastLogin: lastLogin.clone(),
+        };
+        // sans fichier, le prochain lancement se reconnecte simplement
+        let path = session::path(dir);
+        let result = session::save(&path, &saved);
+        self.check_saved(&path, result);
+    }
+
+    fn forget_session(&mut self) {
+        if let Some(dir) = &self.stateDir {
+            let path = session::path(dir);
+            let result = session::clear(&path);
+            self.check_saved(&path, result);
+        }
+    }
+
+    // Garde une session à fermer plus tard, sur le disque pour survivre à un arrêt du programme
+    fn defer_logout(&mut self, logout: PendingLogout) {
+        if !self
+            .pendingLogouts
+            .iter()
+            .any(|other| other.digest == logout.digest)
+        {
+            self.pendingLogouts.push(logout);
+        }
+        self.save_pending();
+    }
+
+    fn save_pending(&mut self) {
+        if let Some(dir) = &self.stateDir {
+            let path = session::pending_path(dir);
+            let result = session::save_pending(&path, &self.pendingLogouts);
+            self.check_saved(&path, result);
+        }
+    }
+
+    // Une session non sauvegardée n'empêche pas la connexion, mais une déconnexion en échec serait
+    // oubliée au lancement suivant: l'erreur reste affichée jusqu'à la prochaine écriture réussie
+    fn check_saved(&mut self, path: &Path, result: io::Result<()>) {
+        match result {
+            Ok(()) => self.stateError = None,
+            Err(err) => {
+                let message = fill(tr().stateNotSaved, &[&path.display(), &err]);
+                if self.stateError.as_ref() != Some(&message) {
+                    self.events
+                        .push(ConnectionEvent::StateNotSaved(message.clone()));
+                }
+                self.stateError = Some(message);
+            }
+        }
+    }
+
+    // Retente la fermeture des sessions restées ouvertes, sauf celles déjà en cours et la session
+    // courante
+    pub fn retry_logouts(&mut self) {
+        for logout in self.pendingLogouts.clone() {
+            let inProgress = self
+                .logoutJobs
+                .iter()
+                .any(|(_, digest)| *digest == logout.digest);
+            if inProgress || self.passwordDigest.as_ref() == Some(&logout.digest) {
+                continue;
+            }
+            let id = self.backend.submit(
+                Action::Logout,
+                Params {
+                    username: Some(logout.username),
+                    digest: Some(logout.digest.clone()),
+                    ..Default::default()
+                },
+            );
+            self.logoutJobs.push((id, logout.digest));
+        }
+    }
+
+    fn on_deferred_logout_result(
+        &mut self,
+        digest: String,
+        result: Result<Payload, ResponseError>,
+    ) {
+        let Some(position) = self
+            .pendingLogouts
+            .iter()
+            .position(|logout| logout.digest == digest)
+        else {
+            return;
+        };
+        let username = self.pendingLogouts[position].username.clone();
+        // en cas d'échec, la session reste dans la liste pour le prochain essai
+        if result.is_ok() {
+            self.pendingLogouts.remove(position);
+            self.save_pending();
+        }
+        self.events.push(ConnectionEvent::DeferredLogout(
+            username,
+            result.map(|_| ()),
+        ));
+    }
+
+    pub fn login(&mut self, username: String, password: String) {
+        self.username = Some(username);
+        self.password = Some(password);
+        self.passwordDigest = None;
+
+        let params = Params {
+            username: self.username.clone(),
+            password: self.password.clone(),
+            ..Default::default()
+        };
+
+        self.status = ConnectionStatus::Connecting;
+        self.connectingSince = Some(Instant::now());
+
+        self.lastLogin = Some(Local::now().format(DATE_FORMAT).to_string());
+
+        self.submit(Action::Login, params);
+    }
+
+    fn on_login_result(&mut self, result: Result<Payload, ResponseError>) {
+        self.connectingSince = None;
+
+        match result {
+            Ok(payload) => {
+                self.status = ConnectionStatus::Connected;
+                self.backoff.reset();
+
+                self.lastPingTimestamp = Some(Local::now());
+                self.lastPingAttempt = Some(Local::now());
+                self.lastError = None;
+
+                self.passwordDigest = payload.digest;
+                self.save_session();
+                // le contrôleur a redonné le digest d'une session à fermer: elle est de nouveau utilisée
+                let digest = self.passwordDigest.clone();
+                self.pendingLogouts
+                    .retain(|logout| Some(&logout.digest) != digest.as_ref());
+                self.save_pending();
+
+                self.events.push(ConnectionEvent::LoggedIn);
+                self.retry_logouts();
+            }
+            Err(err) => {
+                self.status = ConnectionStatus::Disconnected;
+                self.lastError = Some(err.clone());
+                let code = err.code();
+                self.events.push(ConnectionEvent::LoginFailed(err));
+                self.schedule_reconnect(code);
+            }
+        }
+    }
+
+    pub fn cancel_login(&mut self) {
+        self.forget_pending();
+        self.resuming = false;
+        self.backoff.reset();
+        self.connectingSince = None;
+        self.status = ConnectionStatus::Disconnected;
+        self.lastError = Some(ResponseError::new(ErrorCode::Cancelled, ""));
+    }
+
+    pub fn ping(&mut self) {
+        self.lastPingAttempt = Some(Local::now());
+
+        let params = Params {
+            username: self.username.clone(),
+            digest: self.passwordDigest.clone(),
+            ..Default::default()
+        };
+        self.submit(Action::Ping, params);
+    }
+
+    fn on_ping_result(&mut self, result: Result<Payload, ResponseError>, duration: Duration) {
+        let resuming = std::mem::take(&mut self.resuming);
+        match result {
+            Ok(_) => {
+                self.lastPingTimestamp = Some(Local::now());
+                self.lastError = None;
+                self.status = ConnectionStatus::Connected;
+                if resuming {
+                    self.connectingSince = None;
+                    self.backoff.reset();
+                    self.events.push(ConnectionEvent::Resumed);
+                } else {
+                    self.events.push(ConnectionEvent::PingSucceeded(duration));
+                }
+                // le contrôleur répond: c'est le moment de fermer les sessions restées ouvertes
+                self.retry_logouts();
+            }
+            Err(err) if resuming => {
+                // la session sauvegardée ne répond plus: connexion complète, qui la remplacera
+                self.passwordDigest = None;
+                self.events.push(ConnectionEvent::ResumeFailed(err.clone()));
+                match (self.username.clone(), self.password.clone()) {
+                    (Some(username), Some(password)) => self.login(username, password),
+                    _ => {
+                        self.connectingSince = None;
+                        self.status = ConnectionStatus::Disconnected;
+                        self.lastError = Some(err);
+                    }
+                }
+            }
+            Err(err) => {
+                self.status = ConnectionStatus::Disconnected;
+                self.lastError = Some(err.clone());
+                let code = err.code();
+                self.events.push(ConnectionEvent::PingFailed(err, duration));
+                self.schedule_reconnect(code);
+            }
+        }
+    }
+
+    // Ferme la session; renvoie `false` s'il n'y avait aucune session à fermer
+    pub fn disconnect(&mut self) -> bool {
+        // un ping ou une connexion en cours n'a plus d'intérêt
+        self.forget_pending();
+        self.backoff.reset();
+
+        if self.passwordDigest.is_none() {
+            self.status = ConnectionStatus::Disconnected;
+            return false;
+        }
+
+        let params = Params {
+            username: self.username.clone(),
+            digest: self.passwordDigest.clone(),
+            ..Default::default()
+        };
+        self.closing = Some(PendingLogout {
+            username: self.username.clone().unwrap_or_default(),
+            digest: self.passwordDigest.clone().unwrap_or_default(),
+            since: Local::now().format(DATE_FORMAT).to_string(),
+        });
+        self.submit(Action::Logout, params);
+        true
+    }
+
+    // Abandonne une déconnexion en cours, sans attendre la réponse du contrôleur: la session est
+    // gardée pour plus tard, et retirée de la liste si la réponse finit par arriver
+    pub fn abandon(&mut self) {
+        if let (Some(id), Some(closing)) = (self.pendingJob.take(), self.closing.take()) {
+            self.logoutJobs.push((id, closing.digest.clone()));
+            self.defer_logout(closing);
+            self.passwordDigest = None;
+            self.forget_session();
+        }
+        self.forget_pending();
+        self.backoff.reset();
+        self.status = ConnectionStatus::Disconnected;
+    }
+
+    fn on_logout_result(&mut self, result: Result<Payload, ResponseError>) {
+        self.status = ConnectionStatus::Disconnected;
+        self.passwordDigest = None;
+        self.forget_session();
+        let closing = self.closing.take();
+        if let (Err(err), Some(closing)) = (&result, closing) {
+            // la session reste ouverte sur le contrôleur: nouvel essai plus tard
+            self.lastError = Some(err.clone());
+            self.defer_logout(closing);
+        }
+        self.events
+            .push(ConnectionEvent::LoggedOut(result.map(|_| ())));
+    }
+
+    fn schedule_reconnect(&mut self, code: ErrorCode) {
+        // inutile de réessayer avec un mot de passe refusé, sans backend, ou sans mot de passe à
+        // réessayer
+        let hopeless = matches!(
+            code,
+            ErrorCode::InvalidCredentials | ErrorCode::BackendMissing
+        );
+        if hopeless || self.password.is_none() {
+            self.backoff.reset();
+            return;
+        }
+        let delay = self.backoff.schedule();
+        self.events.push(ConnectionEvent::ReconnectScheduled {
+            attempt: self.backoff.attempt(),
+            delay,
+        });
+    }
+
+    pub fn reconnect(&mut self) {
+        self.backoff.start_attempt();
+
+        if let (Some(username), Some(password)) = (self.username.clone(), self.password.clone()) {
+            self.login(username, password);
+        }
+    }
+}
+
+pub fn seconds_since(ts: Option<DateTime<Local>>) -> Option<i64> {
+    let ts = ts?;
+    Some(Local::now().signed_duration_since(ts).num_seconds())
+}
+
+// Explication et conseil pour une erreur, dans la langue de l'interface
+pub fn explain_error(err: &ResponseError) -> (String, &'static str) {
+    let [explanation, hint] = match err.code() {
+        ErrorCode::InvalidRequest => tr().invalidRequest,
+        ErrorCode::Network => tr().networkFailure,
+        ErrorCode::Dns => tr().dnsFailure,
+        ErrorCode::ConnectionRefused => tr().connectionRefused,
+        ErrorCode::Timeout => tr().timeout,
+        ErrorCode::Tls => tr().tlsFailure,
+        ErrorCode::HttpStatus if err.http_status == Some(404) => tr().serverNotFound,
+        ErrorCode::HttpStatus => {
+            let status = err
+                .http_status
+                .map_or("?".to_string(), |status| status.to_string());
+            return (fill(tr().httpStatus[0], &[&status]), tr().httpStatus[1]);
+        }
+        ErrorCode::InvalidCredentials => tr().invalidCredentials,
+        ErrorCode::MalformedResponse => tr().malformedResponse,
+        ErrorCode::SessionExpired => tr().sessionExpired,
+        ErrorCode::BackendMissing => tr().backendMissing,
+        ErrorCode::SpawnFailed => tr().spawnFailed,
+        ErrorCode::BackendUnavailable => tr().backendStopped,
+        ErrorCode::BackendTimeout => tr().backendTimeout,
+        ErrorCode::IncompatibleBackend => tr().incompatibleBackend,
+        ErrorCode::Cancelled => tr().loginCancelled,
+        ErrorCode::Unknown => tr().unknownError,
+    };
+    (explanation.to_string(), hint)
+}
+
+// L'erreur sur une seule ligne, pour le journal et la ligne de commande
+pub fn describe_error(err: &ResponseError) -> String {
+    let (explanation, hint) = explain_error(err);
+    let mut parts = vec![explanation, hint.to_string()];
+    if !err.message.is_empty() {
+        parts.push(fill(tr().errorDetail, &[&err.message]));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockBackend;
+    use std::{env, fs};
+
+    fn mock_connection(script: &str) -> Connection {
+        let mut connection = Connection::new(
+            Box::new(MockBackend::parse(script)),
+            Duration::from_secs(60),
+        );
+        // reconnexion quasi immédiate, pour ne pas attendre le délai réel
+        connection.backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10));
+        connection
+    }
+
+    // Noms des événements reçus jusqu'à `name` inclus
+    fn events_until(connection: &mut Connection, name: &str) -> Vec<&'static str> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut names = Vec::new();
+        while Instant::now() < deadline {
+            names.extend(connection.poll().iter().map(ConnectionEvent::name));
+            if names.contains(&name) {
+                return names;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("pas d'événement {} après {:?}", name, names);
+    }
+
+    fn last_error_code(connection: &Connection) -> Option<&str> {
+        connection.lastError.as_ref().map(|err| err.code.as_str())
+    }
+
+    #[test]
+    fn login_succeeds() {
+        let mut connection = mock_connection("login=ok");
+        connection.login("alice".to_string(), "secret".to_string());
+        assert!(connection.status == ConnectionStatus::Connecting);
+
+        events_until(&mut connection, "logged_in");
+        assert!(connection.status == ConnectionStatus::Connected);
+        assert_eq!(connection.passwordDigest.as_deref(), Some("mock-digest-1"));
+        assert!(connection.lastError.is_none());
+    }
+
+    #[test]
+    fn refused_password_is_not_retried() {
+        let mut connection = mock_connection("login=fail:invalid_credentials");
+        connection.login("alice".to_string(), "wrong".to_string());
+
+        let events = events_until(&mut connection, "login_failed");
+        assert!(!events.contains(&"reconnect_scheduled"));
+        assert!(connection.status == ConnectionStatus::Disconnected);
+        assert_eq!(last_error_code(&connection), Some("invalid_credentials"));
+        assert!(!connection.backoff.is_scheduled());
+    }
+
+    #[test]
+    fn failed_ping_reconnects_after_backoff() {
+        let mut connection = mock_connection("login=ok;ping=fail:network");
+        connection.login("alice".to_string(), "secret".to_string());
+        events_until(&mut connection, "logged_in");
+
+        connection.ping();
+        let events = events_until(&mut connection, "reconnect_scheduled");
+        assert!(events.contains(&"ping_failed"));
+        assert!(connection.status == ConnectionStatus::Disconnected);
+        assert_eq!(last_error_code(&connection), Some("network"));
+        assert_eq!(connection.backoff.attempt(), 1);
+        assert!(connection.backoff.is_scheduled());
+
+        std::thread::sleep(Duration::from_millis(20));
+        connection.tick();
+        let events = events_until(&mut connection, "logged_in");
+        assert_eq!(events, ["reconnecting", "logged_in"]);
+        assert!(connection.status == ConnectionStatus::Connected);
+        assert_eq!(connection.backoff.attempt(), 0);
+    }
+
+    #[test]
+    fn cancelled_login_is_closed_when_it_succeeds() {
+        let mut connection = mock_connection("login=ok@1");
+        connection.login("alice".to_string(), "secret".to_string());
+        connection.cancel_login();
+        assert!(!connection.is_busy());
+        assert!(connection.status == ConnectionStatus::Disconnected);
+        assert_eq!(last_error_code(&connection), Some("cancelled"));
+
+        // la réponse arrive quand même: la session ouverte entre-temps est fermée aussitôt
+        let events = events_until(&mut connection, "deferred_logout");
+        assert!(!events.contains(&"logged_in"));
+        assert!(connection.status == ConnectionStatus::Disconnected);
+        assert!(connection.passwordDigest.is_none());
+        assert!(connection.pendingLogouts.is_empty());
+    }
+
+    #[test]
+    fn failed_logout_is_retried_at_next_launch() {
+        let dir =
+            env::temp_dir().join(format!("connection-internat-logout-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut connection = mock_connection("logout=fail:network");
+        connection.keep_state_in(dir.clone());
+        connection.login("alice".to_string(), "secret".to_string());
+        events_until(&mut connection, "logged_in");
+        assert!(connection.disconnect());
+        events_until(&mut connection, "logout_failed");
+        assert_eq!(connection.pendingLogouts.len(), 1);
+        assert_eq!(connection.pendingLogouts[0].digest, "mock-digest-1");
+
+        // la session restée ouverte est reprise du disque, puis fermée dès que le contrôleur répond
+        let mut connection = mock_connection("logout=ok");
+        connection.keep_state_in(dir.clone());
+        assert_eq!(connection.pendingLogouts.len(), 1);
+        connection.retry_logouts();
+        events_until(&mut connection, "deferred_logout");
+        assert!(connection.pendingLogouts.is_empty());
+        assert!(session::load_pending(&session::pending_path(&dir)).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,263 @@
+use miniserde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Le socket n'existe que sous Unix: ailleurs, seules les déclarations communes restent
+#[cfg(unix)]
+use chrono::{DateTime, Local};
+#[cfg(unix)]
+use miniserde::json;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::time::Duration;
+
+use crate::connection::Connection;
+#[cfg(unix)]
+use crate::connection::{describe_error, ConnectionStatus};
+#[cfg(unix)]
+use crate::i18n::fill;
+use crate::i18n::tr;
+#[cfg(unix)]
+use crate::DATE_FORMAT;
+
+const SOCKET_FILE_NAME: &str = "control.sock";
+// Délai maximal pour lire la commande d'un client: l'interface ne doit pas rester bloquée
+#[cfg(unix)]
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(200);
+// Délai maximal d'attente de la réponse, côté client
+#[cfg(unix)]
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Socket de contrôle: une instance lancée (interface ou `connect`) y répond aux autres processus
+// (barre d'état, script, second terminal). Le client envoie une commande sur une ligne, et reçoit
+// une réponse JSON sur une ligne qui contient toujours l'état de la connexion.
+// Un socket par dossier de configuration, comme le verrou d'instance.
+pub fn socket_path(configDir: &Path) -> PathBuf {
+    // if env variable CONTROL_SOCKET is set, use it
+    if let Ok(path) = std::env::var("CONTROL_SOCKET") {
+        return PathBuf::from(path);
+    }
+    configDir.join(SOCKET_FILE_NAME)
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ControlCommand {
+    Status,
+    Reconnect,
+    PingNow,
+    Disconnect,
+}
+
+impl ControlCommand {
+    #[cfg(unix)]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ControlCommand::Status => "status",
+            ControlCommand::Reconnect => "reconnect",
+            ControlCommand::PingNow => "ping-now",
+            ControlCommand::Disconnect => "disconnect",
+        }
+    }
+
+    pub fn parse(command: &str) -> Option<ControlCommand> {
+        match command {
+            "status" => Some(ControlCommand::Status),
+            "reconnect" => Some(ControlCommand::Reconnect),
+            "ping-now" | "ping" => Some(ControlCommand::PingNow),
+            "disconnect" => Some(ControlCommand::Disconnect),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ControlReply {
+    pub ok: bool,
+    pub message: Option<String>,
+    pub status: String,
+    pub username: Option<String>,
+    pub lastLogin: Option<String>,
+    pub lastPingTimestamp: Option<String>,
+    pub lastError: Option<String>,
+}
+
+#[cfg(unix)]
+impl ControlReply {
+    fn new(connection: &Connection, result: Result<(), String>) -> Self {
+        let (ok, message) = match result {
+            Ok(()) => (true, None),
+            Err(message) => (false, Some(message)),
+        };
+        Self {
+            ok,
+            message,
+            status: connection.status.as_str().to_string(),
+            username: connection.username.clone(),
+            lastLogin: connection.lastLogin.clone(),
+            lastPingTimestamp: connection
+                .lastPingTimestamp
+                .map(|ts: DateTime<Local>| ts.format(DATE_FORMAT).to_string()),
+            lastError: connection.lastError.as_ref().map(describe_error),
+        }
+    }
+}
+
+// jamais créé hors d'Unix, où `bind` échoue toujours
+#[cfg_attr(not(unix), allow(dead_code))]
+pub struct ControlServer {
+    path: PathBuf,
+    #[cfg(unix)]
+    listener: UnixListener,
+}
+
+#[cfg(unix)]
+impl ControlServer {
+    pub fn bind(path: PathBuf) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+                // socket laissé par une instance arrêtée brutalement: on le remplace, sauf si une
+                // instance y répond encore
+                if UnixStream::connect(&path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        fill(tr().controlAlreadyListening, &[&path.display()]),
+                    ));
+                }
+                std::fs::remove_file(&path)?;
+                UnixListener::bind(&path)?
+            }
+            Err(err) => return Err(err),
+        };
+        listener.set_nonblocking(true)?;
+
+        Ok(Self { path, listener })
+    }
+
+    // Répond aux clients en attente, sans bloquer. `status`, `reconnect` et `ping-now` sont traités
+    // ici; renvoie `true` si un client a demandé la déconnexion, que l'appelant doit effectuer.
+    pub fn poll(&mut self, connection: &mut Connection) -> bool {
+        let mut disconnect = false;
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Some(command) = serve(stream, connection) {
+                disconnect |= command == ControlCommand::Disconnect;
+            }
+        }
+        disconnect
+    }
+}
+
+#[cfg(unix)]
+fn serve(stream: UnixStream, connection: &mut Connection) -> Option<ControlCommand> {
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).ok()?;
+
+    let command = ControlCommand::parse(line.trim());
+    let result = match command {
+        Some(command) => execute(command, connection),
+        None => Err(fill(tr().controlUnknownCommand, &[&line.trim()])),
+    };
+    let accepted = result.is_ok();
+
+    let reply = json::to_string(&ControlReply::new(connection, result));
+    (&stream).write_all(format!("{}\n", reply).as_bytes()).ok();
+
+    match accepted {
+        true => command,
+        false => None,
+    }
+}
+
+#[cfg(unix)]
+fn execute(command: ControlCommand, connection: &mut Connection) -> Result<(), String> {
+    match command {
+        ControlCommand::Status => Ok(()),
+        ControlCommand::Reconnect => {
+            if connection.is_busy() {
+                return Err(tr().controlBusy.to_string());
+            }
+            if connection.password.is_none() {
+                return Err(tr().controlNoCredentials.to_string());
+            }
+            connection.reconnect();
+            Ok(())
+        }
+        ControlCommand::PingNow => {
+            if connection.is_busy() {
+                return Err(tr().controlBusy.to_string());
+            }
+            if connection.status != ConnectionStatus::Connected
+                || connection.passwordDigest.is_none()
+            {
+                return Err(tr().controlNoSession.to_string());
+            }
+            connection.ping();
+            Ok(())
+        }
+        ControlCommand::Disconnect => match connection.passwordDigest {
+            Some(_) => Ok(()),
+            None => Err(tr().controlNoSession.to_string()),
+        },
+    }
+}
+
+#[cfg(not(unix))]
+impl ControlServer {
+    pub fn bind(_path: PathBuf) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            tr().controlUnsupported,
+        ))
+    }
+
+    pub fn poll(&mut self, _connection: &mut Connection) -> bool {
+        false
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        // déjà supprimé ou dossier disparu: une socket restante est remplacée au lancement suivant
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Côté client: envoie une commande à l'instance en cours et renvoie sa réponse
+#[cfg(unix)]
+pub fn send(path: &Path, command: ControlCommand) -> io::Result<ControlReply> {
+    let stream = UnixStream::connect(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            fill(tr().controlNoInstance, &[&path.display(), &err]),
+        )
+    })?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    (&stream).write_all(format!("{}\n", command.as_str()).as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    json::from_str::<ControlReply>(line.trim()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            fill(tr().controlInvalidReply, &[&line.trim()]),
+        )
+    })
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &Path, _command: ControlCommand) -> io::Result<ControlReply> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        tr().controlUnsupported,
+    ))
+}
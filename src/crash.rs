@@ -0,0 +1,217 @@
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use ratatui::crossterm::{event::DisableBracketedPaste, execute};
+
+use crate::backend::{self, Session};
+use crate::connection::ConnectionEvent;
+use crate::daemon::LOGOUT_TIMEOUT;
+use crate::i18n::{fill, tr};
+use crate::session;
+use crate::DATE_FORMAT;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+// Événements gardés pour le rapport de plantage
+const RECENT_EVENTS: usize = 20;
+
+// Filet de sécurité en cas de panique: le terminal est rendu dans son état normal, la session ouverte
+// est fermée par un nouveau processus backend, et un rapport `crash-<date>.txt` est écrit à côté de
+// la configuration. Le programme s'arrête ensuite, quel que soit le thread qui a paniqué.
+// La même déconnexion sert à un arrêt demandé par un signal quand l'interface ne répond plus.
+struct CrashState {
+    backendPath: String,
+    portalUrl: Option<String>,
+    // dossier de la configuration: rapport, session sauvegardée et sessions restées ouvertes
+    reportDir: PathBuf,
+    // le terminal est en mode brut, à restaurer
+    tui: bool,
+    session: Option<Session>,
+    events: VecDeque<String>,
+}
+
+static STATE: Mutex<Option<CrashState>> = Mutex::new(None);
+
+pub fn install(backendPath: String, portalUrl: Option<String>, reportDir: PathBuf, tui: bool) {
+    if let Ok(mut state) = STATE.lock() {
+        *state = Some(CrashState {
+            backendPath,
+            portalUrl,
+            reportDir,
+            tui,
+            session: None,
+            events: VecDeque::new(),
+        });
+    }
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // le verrou peut être tenu par le thread qui a paniqué: on fait sans
+        let state = STATE.try_lock().ok().and_then(|mut state| state.take());
+
+        if state.as_ref().is_some_and(|state| state.tui) {
+            // terminal peut-être déjà disparu: le rapport compte plus que son état
+            let _ = execute!(io::stdout(), DisableBracketedPaste);
+            ratatui::restore();
+        }
+        default_hook(info);
+
+        if let Some(state) = state {
+            let logout = logout(&state);
+            match write_report(&state, info, &logout) {
+                Ok(path) => eprintln!("{}", fill(tr().crashReportWritten, &[&path.display()])),
+                Err(err) => eprintln!("{}", fill(tr().crashReportFailed, &[&err])),
+            }
+        }
+        std::process::exit(101);
+    }));
+}
+
+// Le backend a changé d'emplacement depuis le lancement
+pub fn set_backend_path(path: &str) {
+    if let Ok(mut state) = STATE.lock() {
+        if let Some(state) = state.as_mut() {
+            state.backendPath = path.to_string();
+        }
+    }
+}
+
+// Session à fermer en cas de plantage; `None` une fois la session fermée
+pub fn remember_session(username: Option<String>, digest: Option<String>) {
+    if let Ok(mut state) = STATE.lock() {
+        if let Some(state) = state.as_mut() {
+            state.session = match (username, digest) {
+                (Some(username), Some(digest)) => Some(Session { username, digest }),
+                _ => None,
+            };
+        }
+    }
+}
+
+pub fn record(event: &ConnectionEvent) {
+    if let Ok(mut state) = STATE.lock() {
+        if let Some(state) = state.as_mut() {
+            if state.events.len() == RECENT_EVENTS {
+                state.events.pop_front();
+            }
+            state.events.push_back(format!(
+                "{} {}",
+                Local::now().format(DATE_FORMAT),
+                event.describe()
+            ));
+        }
+    }
+}
+
+// Ferme la session depuis un autre thread que l'interface, quand sa boucle ne répond plus (terminal
+// disparu après un SIGHUP); ne fait rien sans session ouverte
+pub fn logout_now() {
+    let state = match STATE.lock() {
+        Ok(state) => state.as_ref().map(|state| {
+            (
+                state.backendPath.clone(),
+                state.portalUrl.clone(),
+                state.reportDir.clone(),
+                state.session.clone(),
+            )
+        }),
+        Err(_) => None,
+    };
+    if let Some((backendPath, portalUrl, configDir, Some(session))) = state {
+        let saved = match close_session(backendPath, portalUrl, session.clone()) {
+            Ok(()) => session::clear(&session::path(&configDir)),
+            Err(_) => defer_logout(&configDir, session),
+        };
+        if let Err(err) = saved {
+            eprintln!("{}", fill(tr().crashStateNotSaved, &[&err]));
+        }
+    }
+}
+
+// Résultat de la déconnexion de secours, pour le rapport
+fn logout(state: &CrashState) -> String {
+    let session = match &state.session {
+        Some(session) => session.clone(),
+        None => return tr().crashNoSession.to_string(),
+    };
+    eprintln!("{}", fill(tr().crashClosingSession, &[&session.username]));
+    let username = session.username.clone();
+    match close_session(
+        state.backendPath.clone(),
+        state.portalUrl.clone(),
+        session.clone(),
+    ) {
+        Ok(()) => match session::clear(&session::path(&state.reportDir)) {
+            Ok(()) => fill(tr().crashSessionClosed, &[&username]),
+            Err(err) => fill(tr().crashSessionStillSaved, &[&username, &err]),
+        },
+        Err(err) => match defer_logout(&state.reportDir, session) {
+            Ok(()) => fill(tr().crashLogoutDeferred, &[&username, &err]),
+            Err(saveErr) => fill(tr().crashLogoutLost, &[&username, &err, &saveErr]),
+        },
+    }
+}
+
+// La session restera à fermer au prochain lancement
+fn defer_logout(configDir: &Path, unclosed: Session) -> io::Result<()> {
+    session::defer_logout(
+        &session::pending_path(configDir),
+        session::PendingLogout {
+            username: unclosed.username,
+            digest: unclosed.digest,
+            since: Local::now().format(DATE_FORMAT).to_string(),
+        },
+    )
+}
+
+// Déconnexion par un nouveau processus backend, en au plus `LOGOUT_TIMEOUT`
+fn close_session(
+    backendPath: String,
+    portalUrl: Option<String>,
+    session: Session,
+) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut backend = backend::from_path(&backendPath, portalUrl);
+        // après `LOGOUT_TIMEOUT`, plus personne n'attend la réponse
+        let _ = sender.send(backend.logout(&session));
+    });
+    match receiver.recv_timeout(LOGOUT_TIMEOUT) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(format!("{} ({})", err.message, err.code)),
+        Err(_) => Err(fill(tr().crashNoAnswer, &[&LOGOUT_TIMEOUT.as_secs()])),
+    }
+}
+
+fn write_report(state: &CrashState, info: &PanicHookInfo, logout: &str) -> io::Result<PathBuf> {
+    let now = Local::now();
+    let events: String = state
+        .events
+        .iter()
+        .map(|event| format!("  {}\n", event))
+        .collect();
+    let report = fill(
+        tr().crashReport,
+        &[
+            &VERSION,
+            &now.format(DATE_FORMAT),
+            info,
+            &logout,
+            &events,
+            &Backtrace::force_capture(),
+        ],
+    );
+
+    fs::create_dir_all(&state.reportDir)?;
+    let path = state
+        .reportDir
+        .join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S")));
+    fs::write(&path, report)?;
+    Ok(path)
+}
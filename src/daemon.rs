@@ -0,0 +1,159 @@
+use chrono::Local;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::connection::{describe_error, Connection, ConnectionEvent, ConnectionStatus};
+use crate::control::ControlServer;
+use crate::i18n::{fill, tr};
+use crate::{DATE_FORMAT, TICK_RATE};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Délai maximal accordé à la déconnexion lors de l'arrêt (ici, et dans l'interface)
+pub const LOGOUT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Mode sans interface (`connect`, ou `--daemon`): se connecte, garde la session ouverte, et écrit
+// chaque changement d'état sur la sortie d'erreur au lieu de dessiner des écrans.
+// Pensé pour systemd, une session SSH ou un Raspberry Pi allumé en permanence.
+pub fn run(
+    mut connection: Connection,
+    username: String,
+    password: String,
+    socketPath: PathBuf,
+) -> io::Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst))
+        .map_err(io::Error::other)?;
+
+    let mut control = match ControlServer::bind(socketPath) {
+        Ok(control) => Some(control),
+        Err(err) => {
+            log(&fill(tr().controlUnavailable, &[&err]));
+            None
+        }
+    };
+
+    // fermées avant d'en ouvrir une autre, le contrôleur pouvant redonner le même digest
+    if !connection.pendingLogouts.is_empty() {
+        log(&fill(
+            tr().logRetryingLogouts,
+            &[&connection.pendingLogouts.len()],
+        ));
+        connection.retry_logouts();
+    }
+    match connection.saved_session(&username) {
+        Some(saved) => log(&fill(tr().logResuming, &[&username, &saved.lastLogin])),
+        None => log(&fill(tr().logConnecting, &[&username])),
+    }
+    connection.open(username, Some(password));
+
+    let tick_rate = Duration::from_millis(TICK_RATE);
+    let mut last_tick = Instant::now();
+
+    while running.load(Ordering::SeqCst) {
+        if let Some(control) = &mut control {
+            if control.poll(&mut connection) {
+                log(tr().logControlDisconnect);
+                break;
+            }
+        }
+
+        for event in connection.poll() {
+            log_event(&connection, &event);
+        }
+
+        if connection.status == ConnectionStatus::Disconnected
+            && !connection.backoff.is_scheduled()
+            && !connection.is_busy()
+        {
+            // identifiants refusés: réessayer ne servirait à rien
+            log(tr().logGivingUp);
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "connexion impossible",
+            ));
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            connection.tick();
+            last_tick = Instant::now();
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    if !running.load(Ordering::SeqCst) {
+        log(tr().logStopRequested);
+    }
+    if connection.disconnect() {
+        for event in connection.wait(LOGOUT_TIMEOUT) {
+            log_event(&connection, &event);
+        }
+        if connection.is_busy() {
+            log(tr().logLogoutTimedOut);
+            connection.abandon();
+        }
+    }
+
+    Ok(())
+}
+
+fn log_event(connection: &Connection, event: &ConnectionEvent) {
+    match event {
+        ConnectionEvent::LoggedIn => log(&fill(
+            tr().logLoggedIn,
+            &[&connection.username.as_deref().unwrap_or_default()],
+        )),
+        ConnectionEvent::LoginFailed(_) => {
+            log(&fill(tr().logLoginFailed, &[&last_error(connection)]))
+        }
+        ConnectionEvent::Resumed => log(&fill(
+            tr().logResumed,
+            &[&connection.username.as_deref().unwrap_or_default()],
+        )),
+        ConnectionEvent::ResumeFailed(err) => {
+            log(&fill(tr().logResumeFailed, &[&describe_error(err)]))
+        }
+        // un ping réussi n'est pas un changement d'état
+        ConnectionEvent::PingSucceeded(_) => {}
+        ConnectionEvent::PingFailed(..) => {
+            log(&fill(tr().logPingFailed, &[&last_error(connection)]))
+        }
+        ConnectionEvent::ReconnectScheduled { attempt, delay } => log(&fill(
+            tr().logReconnectScheduled,
+            &[attempt, &delay.as_secs()],
+        )),
+        ConnectionEvent::Reconnecting { attempt } => log(&fill(tr().logReconnecting, &[attempt])),
+        ConnectionEvent::LoggedOut(Ok(())) => log(tr().logLoggedOut),
+        ConnectionEvent::LoggedOut(Err(err)) => {
+            log(&fill(tr().logLogoutFailed, &[&describe_error(err)]))
+        }
+        ConnectionEvent::DeferredLogout(username, Ok(())) => {
+            log(&fill(tr().logDeferredLogoutClosed, &[username]))
+        }
+        ConnectionEvent::DeferredLogout(username, Err(err)) => log(&fill(
+            tr().logDeferredLogoutFailed,
+            &[username, &describe_error(err)],
+        )),
+        ConnectionEvent::StateNotSaved(message) | ConnectionEvent::EventLogFailed(message) => {
+            log(message)
+        }
+    }
+}
+
+// Le message d'erreur affiché par l'interface, sur une seule ligne
+fn last_error(connection: &Connection) -> String {
+    connection
+        .lastError
+        .as_ref()
+        .map(describe_error)
+        .unwrap_or_default()
+}
+
+pub fn log(message: &str) {
+    eprintln!("[{}] {}", Local::now().format(DATE_FORMAT), message);
+}
@@ -0,0 +1,125 @@
+use chrono::{Local, SecondsFormat};
+use miniserde::{json, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::connection::ConnectionEvent;
+
+const EVENT_LOG_FILE_NAME: &str = "events.log";
+// Au-delà, le journal est renommé en `events.log.1` (l'ancien `.1` devient `.2`, etc.)
+const MAX_SIZE: u64 = 1024 * 1024;
+const ROTATIONS: u32 = 3;
+
+// Journal des événements de connexion, gardé d'un lancement à l'autre pour répondre à « combien de
+// coupures cette nuit ? ». Une ligne JSON par événement, ajoutée en fin de fichier:
+// {"time":"2026-10-18T02:14:07+02:00","event":"ping_failed","username":"jdupont","code":"network",...}
+// Les champs sans objet valent `null`; le digest de session n'y figure jamais.
+#[derive(Serialize)]
+struct Entry {
+    time: String,
+    event: String,
+    username: Option<String>,
+    code: Option<String>,
+    message: Option<String>,
+    httpStatus: Option<u16>,
+    durationMs: Option<u64>,
+    attempt: Option<u32>,
+    delaySecs: Option<u64>,
+}
+
+impl Entry {
+    fn new(event: &ConnectionEvent, username: Option<String>) -> Self {
+        let error = event.error();
+        let mut entry = Self {
+            time: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            event: event.name().to_string(),
+            username,
+            code: error.map(|err| err.code.clone()),
+            message: error.map(|err| err.message.clone()),
+            httpStatus: error.and_then(|err| err.http_status),
+            durationMs: None,
+            attempt: None,
+            delaySecs: None,
+        };
+        match event {
+            ConnectionEvent::PingSucceeded(duration) | ConnectionEvent::PingFailed(_, duration) => {
+                entry.durationMs = Some(duration.as_millis() as u64);
+            }
+            ConnectionEvent::ReconnectScheduled { attempt, delay } => {
+                entry.attempt = Some(*attempt);
+                entry.delaySecs = Some(delay.as_secs());
+            }
+            ConnectionEvent::Reconnecting { attempt } => entry.attempt = Some(*attempt),
+            // la session fermée n'est pas forcément celle en cours
+            ConnectionEvent::DeferredLogout(username, _) => entry.username = Some(username.clone()),
+            ConnectionEvent::StateNotSaved(message) | ConnectionEvent::EventLogFailed(message) => {
+                entry.message = Some(message.clone())
+            }
+            _ => {}
+        }
+        entry
+    }
+}
+
+pub struct EventLog {
+    path: PathBuf,
+}
+
+impl EventLog {
+    pub fn new(configDir: &Path) -> Self {
+        Self {
+            path: configDir.join(EVENT_LOG_FILE_NAME),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Une écriture impossible (disque plein, dossier en lecture seule) ne doit pas gêner la connexion:
+    // l'erreur est seulement renvoyée, pour être signalée. L'événement est écrit même si la rotation
+    // a échoué, le journal dépassant alors sa taille maximale.
+    pub fn record(&self, event: &ConnectionEvent, username: Option<String>) -> io::Result<()> {
+        let line = format!("{}\n", json::to_string(&Entry::new(event, username)));
+        let rotated = self.rotate(line.len() as u64);
+        self.append(&line)?;
+        rotated
+    }
+
+    fn append(&self, line: &str) -> io::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&self.path)?.write_all(line.as_bytes())
+    }
+
+    fn rotate(&self, incoming: u64) -> io::Result<()> {
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            // premier événement: rien à renommer
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if size + incoming <= MAX_SIZE {
+            return Ok(());
+        }
+        for index in (1..ROTATIONS).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn rotated(&self, index: u32) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+}
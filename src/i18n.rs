@@ -0,0 +1,1027 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Langue de l'interface: `language` dans la configuration, sinon la locale du système
+// (`LC_ALL`, `LC_MESSAGES`, puis `LANG`). Le français reste la langue par défaut; toute autre langue
+// que le français donne l'anglais.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Language {
+    French,
+    English,
+}
+
+impl Language {
+    // `fr`, `en`, ou une locale comme `fr_FR.UTF-8`; `None` pour une valeur inconnue
+    pub fn parse(value: &str) -> Option<Self> {
+        let code = value
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match code.as_str() {
+            "fr" => Some(Language::French),
+            "en" => Some(Language::English),
+            _ => None,
+        }
+    }
+
+    pub fn detect(configured: Option<&str>) -> Self {
+        if let Some(language) = configured.and_then(Self::parse) {
+            return language;
+        }
+        for variable in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+            match env::var(variable) {
+                // locale neutre: pas de préférence exprimée
+                Ok(value) if value.is_empty() || value == "C" || value == "POSIX" => continue,
+                Ok(value) => return Self::parse(&value).unwrap_or(Language::English),
+                Err(_) => continue,
+            }
+        }
+        Language::French
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        match self {
+            Language::French => &FRENCH,
+            Language::English => &ENGLISH,
+        }
+    }
+}
+
+// Langue choisie: `NOT_CHOSEN` tant qu'aucun message n'a été demandé, sinon `Language as u8`
+static LANGUAGE: AtomicU8 = AtomicU8::new(NOT_CHOSEN);
+const NOT_CHOSEN: u8 = u8::MAX;
+
+// Choisit la langue d'après la configuration. Peut suivre des messages déjà produits dans la langue
+// de la locale (arguments invalides): les suivants passent dans la langue choisie.
+pub fn init(configured: Option<&str>) {
+    LANGUAGE.store(Language::detect(configured) as u8, Ordering::Relaxed);
+}
+
+// Messages dans la langue choisie; sans appel à `init`, seule la locale compte
+pub fn tr() -> &'static Catalog {
+    let language = match LANGUAGE.load(Ordering::Relaxed) {
+        value if value == Language::French as u8 => Language::French,
+        value if value == Language::English as u8 => Language::English,
+        _ => {
+            let language = Language::detect(None);
+            // un `init` concurrent garde la main
+            let _ = LANGUAGE.compare_exchange(
+                NOT_CHOSEN,
+                language as u8,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+            language
+        }
+    };
+    language.catalog()
+}
+
+// Remplace chaque `{}` du message, dans l'ordre, par les valeurs données
+pub fn fill(template: &str, values: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for (index, part) in parts.enumerate() {
+        if let Some(value) = values.get(index) {
+            filled.push_str(&value.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+// Catalogue des messages de l'interface; `{}` marque une valeur remplie par `fill`
+pub struct Catalog {
+    // Menus
+    pub menuTitle: &'static str,
+    pub connect: &'static str,
+    pub connectAs: &'static str,
+    pub showStatus: &'static str,
+    pub enterCredentials: &'static str,
+    pub forgetCredentials: &'static str,
+    pub quit: &'static str,
+    pub cancel: &'static str,
+    pub reconnect: &'static str,
+    pub disconnect: &'static str,
+    pub disconnectAndQuit: &'static str,
+    pub checkAgain: &'static str,
+    pub whatToDo: &'static str,
+
+    // Champs
+    pub usernameLabel: &'static str,
+    pub passwordLabel: &'static str,
+    pub passphraseLabel: &'static str,
+    pub revealHint: &'static str,
+    pub hideHint: &'static str,
+
+    // Écrans
+    pub unlockHelp: [&'static str; 2],
+    pub updatedTo: &'static str,
+    pub updatedFrom: &'static str,
+    pub keyringNotice: &'static str,
+    pub signalHandlerFailed: &'static str,
+    pub confirmQuit: &'static str,
+    pub disconnecting: &'static str,
+    pub searchedLocations: &'static str,
+    pub originOption: &'static str,
+    pub originEnvironment: &'static str,
+    pub originConfig: &'static str,
+    pub originNextToProgram: &'static str,
+    pub originWorkingDirectory: &'static str,
+
+    // Pieds de page
+    pub footerHome: &'static str,
+    pub footerUsername: &'static str,
+    pub footerPassword: &'static str,
+    pub footerNextStep: &'static str,
+    pub enterKey: &'static str,
+    pub footerStatus: &'static str,
+    pub footerBackend: &'static str,
+
+    // Statut de la connexion
+    pub statusLine: &'static str,
+    pub lastLogin: &'static str,
+    pub lastPing: &'static str,
+    pub lastPingAgo: &'static str,
+    pub lastPingRetry: &'static str,
+    pub backendLine: &'static str,
+    pub backendNotStarted: &'static str,
+    pub mockSessions: &'static str,
+    pub notAvailable: &'static str,
+    pub statusUninitialized: &'static str,
+    pub statusConnected: &'static str,
+    pub statusDisconnected: &'static str,
+    pub statusConnecting: &'static str,
+    pub retryIn: &'static str,
+    pub retry: &'static str,
+    pub error: &'static str,
+    pub pendingLogouts: &'static str,
+    pub pendingLogout: &'static str,
+    pub stateNotSaved: &'static str,
+    pub eventLogFailed: &'static str,
+
+    // Erreurs de connexion: explication, puis conseil
+    pub errorDetail: &'static str,
+    pub invalidRequest: [&'static str; 2],
+    pub networkFailure: [&'static str; 2],
+    pub dnsFailure: [&'static str; 2],
+    pub connectionRefused: [&'static str; 2],
+    pub timeout: [&'static str; 2],
+    pub tlsFailure: [&'static str; 2],
+    pub httpStatus: [&'static str; 2],
+    pub serverNotFound: [&'static str; 2],
+    pub invalidCredentials: [&'static str; 2],
+    pub malformedResponse: [&'static str; 2],
+    pub sessionExpired: [&'static str; 2],
+    pub backendMissing: [&'static str; 2],
+    pub spawnFailed: [&'static str; 2],
+    pub backendStopped: [&'static str; 2],
+    pub backendTimeout: [&'static str; 2],
+    pub incompatibleBackend: [&'static str; 2],
+    pub loginCancelled: [&'static str; 2],
+    pub unknownError: [&'static str; 2],
+
+    // Détail des erreurs du backend (message technique, après l'explication)
+    pub backendNotFound: &'static str,
+    // droits d'exécution vérifiés seulement sous Unix
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub backendNotExecutable: &'static str,
+    pub backendNotStartable: &'static str,
+    pub backendNoPipes: &'static str,
+    pub versionTimeout: &'static str,
+    pub versionUnreadable: &'static str,
+    pub requestNotWritten: &'static str,
+    pub backendNoAnswer: &'static str,
+    pub backendExited: &'static str,
+    pub backendThreadStopped: &'static str,
+    pub noOpenSession: &'static str,
+    pub loginWithoutDigest: &'static str,
+    // backend simulé
+    pub mockError: &'static str,
+    pub mockInvalidScript: &'static str,
+    pub mockInvalidDelay: &'static str,
+    pub mockInvalidResult: &'static str,
+    pub mockMissingResult: &'static str,
+    pub mockUnknownAction: &'static str,
+
+    // Ligne de commande
+    pub usage: &'static str,
+    pub optionNeedsValue: &'static str,
+    pub invalidInterval: &'static str,
+    pub controlNeedsOrder: &'static str,
+    pub unknownOrder: &'static str,
+    pub unknownArgument: &'static str,
+    pub oneCommandOnly: &'static str,
+    pub noSavedUsername: &'static str,
+    pub noSavedPassword: &'static str,
+    pub usernamePrompt: &'static str,
+    pub passwordPrompt: &'static str,
+    pub emptyUsername: &'static str,
+    pub cancelled: &'static str,
+    pub pendingUpdate: &'static str,
+    pub savedUsername: &'static str,
+    pub noSavedUsernameStatus: &'static str,
+    pub passwordEncrypted: &'static str,
+    pub passwordInKeyring: &'static str,
+    pub withoutPassword: &'static str,
+    pub withPassword: &'static str,
+    pub savedSession: &'static str,
+    pub noSavedSession: &'static str,
+    pub noPendingLogouts: &'static str,
+    pub pendingLogoutsHeader: &'static str,
+    pub pendingLogoutSince: &'static str,
+    pub eventLogPath: &'static str,
+    pub controlSocket: &'static str,
+    pub pingInterval: &'static str,
+    pub controller: &'static str,
+    pub connectOnStart: &'static str,
+    pub yes: &'static str,
+    pub no: &'static str,
+    pub sessionUnavailable: &'static str,
+    pub sessionClosed: &'static str,
+    pub logoutFailed: &'static str,
+    pub logoutTimedOut: &'static str,
+    pub credentialsSaved: &'static str,
+    pub passwordStoredInKeyring: &'static str,
+    pub credentialsForgotten: &'static str,
+    pub nothingToEncrypt: &'static str,
+    pub newPassphrasePrompt: &'static str,
+    pub confirmPassphrasePrompt: &'static str,
+    pub passphraseMismatch: &'static str,
+    pub passwordSealed: &'static str,
+    pub passwordInClear: &'static str,
+    pub passphrasePrompt: &'static str,
+    pub passphraseRequired: &'static str,
+    pub attachHint: &'static str,
+    pub attachUnreachable: &'static str,
+    pub replyStatus: &'static str,
+    pub replyUsername: &'static str,
+    pub replyLastLogin: &'static str,
+    pub replyLastPing: &'static str,
+    pub replyLastError: &'static str,
+
+    // Instance déjà lancée
+    pub instanceOwner: &'static str,
+    pub instanceRunning: &'static str,
+    pub instanceRunningUnknown: &'static str,
+    pub lockFailed: &'static str,
+    pub staleLock: &'static str,
+
+    // Journal du mode sans interface
+    pub logRetryingLogouts: &'static str,
+    pub logResuming: &'static str,
+    pub logConnecting: &'static str,
+    pub logControlDisconnect: &'static str,
+    pub logGivingUp: &'static str,
+    pub logStopRequested: &'static str,
+    pub logLogoutTimedOut: &'static str,
+    pub logLoggedIn: &'static str,
+    pub logLoginFailed: &'static str,
+    pub logResumed: &'static str,
+    pub logResumeFailed: &'static str,
+    pub logPingFailed: &'static str,
+    pub logReconnectScheduled: &'static str,
+    pub logReconnecting: &'static str,
+    pub logLoggedOut: &'static str,
+    pub logLogoutFailed: &'static str,
+    pub logDeferredLogoutClosed: &'static str,
+    pub logDeferredLogoutFailed: &'static str,
+
+    // Configuration
+    pub configIoError: &'static str,
+    pub configSyntaxError: &'static str,
+    pub configVaultError: &'static str,
+    pub configLayoutError: &'static str,
+    pub vaultMalformed: &'static str,
+    pub vaultWrongPassphrase: &'static str,
+    pub migrationSchema: &'static str,
+    pub migrationKeyValue: &'static str,
+    pub configMoved: &'static str,
+    pub configNewer: &'static str,
+    pub configBackedUp: &'static str,
+    pub layoutSchemaHeader: &'static str,
+    pub layoutLineCount: &'static str,
+    pub layoutVersion: &'static str,
+    pub syntaxSection: &'static str,
+    pub syntaxKeyValue: &'static str,
+    pub syntaxKeyName: &'static str,
+    pub syntaxMissingValue: &'static str,
+    pub syntaxInvalidValue: &'static str,
+    pub syntaxTrailing: &'static str,
+    pub syntaxEscape: &'static str,
+    pub syntaxQuote: &'static str,
+    pub syntaxDuplicate: &'static str,
+    pub syntaxExpected: &'static str,
+    pub syntaxExpectedFound: &'static str,
+    pub kindText: &'static str,
+    pub kindNumber: &'static str,
+    pub kindBoolean: &'static str,
+    pub kindQuotedText: &'static str,
+    pub kindPositiveNumber: &'static str,
+
+    // Trousseau
+    pub keyringPasswordMissing: &'static str,
+    pub keyringKeptInFile: &'static str,
+    // seulement sous Linux
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub keyringUnreadable: &'static str,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub keyringUnavailable: &'static str,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub keyringLocked: &'static str,
+    // seulement hors de Linux
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub keyringUnsupported: &'static str,
+
+    // Socket de contrôle
+    pub controlUnavailable: &'static str,
+    // seulement sous Unix
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlUnknownCommand: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlBusy: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlNoCredentials: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlNoSession: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlAlreadyListening: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlNoInstance: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub controlInvalidReply: &'static str,
+    // seulement hors d'Unix
+    #[cfg_attr(unix, allow(dead_code))]
+    pub controlUnsupported: &'static str,
+
+    // Rapport de plantage
+    pub crashReportWritten: &'static str,
+    pub crashReportFailed: &'static str,
+    pub crashStateNotSaved: &'static str,
+    pub crashNoSession: &'static str,
+    pub crashClosingSession: &'static str,
+    pub crashSessionClosed: &'static str,
+    pub crashSessionStillSaved: &'static str,
+    pub crashLogoutDeferred: &'static str,
+    pub crashLogoutLost: &'static str,
+    pub crashNoAnswer: &'static str,
+    // version, date, panique, déconnexion, événements (une ligne chacun), pile d'appels
+    pub crashReport: &'static str,
+}
+
+pub const FRENCH: Catalog = Catalog {
+    menuTitle: "Actions",
+    connect: "Se connecter",
+    connectAs: "Se connecter (en tant que {})",
+    showStatus: "Voir la connexion en cours",
+    enterCredentials: "Rentrer ses identifiants",
+    forgetCredentials: "Oublier les identifiants sauvegardés",
+    quit: "Quitter",
+    cancel: "Annuler",
+    reconnect: "Essayer de se reconnecter",
+    disconnect: "Se déconnecter",
+    disconnectAndQuit: "Se déconnecter et quitter",
+    checkAgain: "Vérifier à nouveau",
+    whatToDo: "Que souhaitez vous faire ?",
+
+    usernameLabel: "Identifiant",
+    passwordLabel: "Mot de passe",
+    passphraseLabel: "Phrase de passe",
+    revealHint: "F2: afficher",
+    hideHint: "F2: masquer",
+
+    unlockHelp: [
+        "Le mot de passe sauvegardé est chiffré.",
+        "Entrez la phrase de passe pour le déverrouiller (Échap pour quitter)",
+    ],
+    updatedTo: "Mis à jour en version {}",
+    updatedFrom: "Mis à jour: {} → {}",
+    keyringNotice: "Trousseau: {}",
+    signalHandlerFailed: "Signaux non interceptés ({}): un arrêt par signal laissera la session ouverte",
+    confirmQuit: "Vous êtes connecté en tant que {}. La session sera fermée avant de quitter.",
+    disconnecting: "Déconnexion en cours...",
+    searchedLocations: "Emplacements cherchés:",
+    originOption: "option --backend",
+    originEnvironment: "variable BACKEND_PATH",
+    originConfig: "backend_path dans la configuration",
+    originNextToProgram: "à côté du programme",
+    originWorkingDirectory: "dossier courant",
+
+    footerHome: " Utilisez les flèches ↓↑ pour naviguer dans le menu, Entrée pour valider. ",
+    footerUsername: " Indiquez votre identifiant. Pressez ",
+    footerPassword: " Entrez votre mot de passe. Pressez ",
+    footerNextStep: " pour passer à l'étape suivante ",
+    enterKey: "Entrée",
+    footerStatus: " Échap: retour à l'accueil, la connexion reste ouverte ",
+    footerBackend: " Échap: quitter ",
+
+    statusLine: "Statut: {}",
+    lastLogin: "Dernier login: {}",
+    lastPing: "Dernier ping: {}",
+    lastPingAgo: "{} (il y a {} secondes)",
+    lastPingRetry: "{} (il y a {}s) (essai il y a {}s)",
+    backendLine: "Backend {}: {}",
+    backendNotStarted: "{} (pas encore lancé)",
+    mockSessions: "{} session(s) simulée(s)",
+    notAvailable: "N/A",
+    statusUninitialized: "Non initialisé",
+    statusConnected: "Connecté",
+    statusDisconnected: "Déconnecté",
+    statusConnecting: "Connexion en cours... {} ({}s)",
+    retryIn: "Reconnexion automatique: tentative {} dans {}s",
+    retry: "Reconnexion automatique: tentative {}",
+    error: "Erreur: {}",
+    pendingLogouts: "Sessions restées ouvertes, fermées dès que le contrôleur répond:",
+    pendingLogout: "{} (déconnexion en échec depuis le {})",
+    stateNotSaved: "Impossible d'écrire {}: {}",
+    eventLogFailed: "Journal des événements {} impossible à écrire: {}",
+
+    errorDetail: "Détail: {}",
+    invalidRequest: [
+        "Le backend a refusé la requête.",
+        "Vérifiez que le backend est de la même version que l'application.",
+    ],
+    networkFailure: [
+        "Le contrôleur est injoignable.",
+        "Vérifiez que vous êtes connecté au réseau de l'internat (câble ou Wi-Fi).",
+    ],
+    dnsFailure: [
+        "Erreur de résolution DNS: l'adresse du contrôleur est inconnue.",
+        "Vérifiez que vous êtes connecté au bon réseau, et que vous n'avez pas de VPN allumé.",
+    ],
+    connectionRefused: [
+        "Le contrôleur a refusé la connexion.",
+        "Vérifiez l'adresse `portal_url` de la configuration, ou réessayez dans quelques minutes.",
+    ],
+    timeout: [
+        "Le contrôleur n'a pas répondu à temps.",
+        "Le réseau est peut-être saturé: réessayez dans quelques instants.",
+    ],
+    tlsFailure: [
+        "La connexion sécurisée avec le contrôleur a échoué.",
+        "Vérifiez l'adresse `portal_url` et qu'aucun proxy n'intercepte la connexion.",
+    ],
+    httpStatus: [
+        "Le contrôleur a répondu avec le code HTTP {}.",
+        "Le contrôleur a sans doute un problème passager: réessayez plus tard.",
+    ],
+    serverNotFound: [
+        "Incapable de trouver le serveur.",
+        "Vérifiez que vous êtes connecté au bon réseau.",
+    ],
+    invalidCredentials: [
+        "Identifiants incorrects.",
+        "Rentrez à nouveau votre identifiant et votre mot de passe depuis l'accueil.",
+    ],
+    malformedResponse: [
+        "Le contrôleur a envoyé une réponse incompréhensible.",
+        "Le portail a peut-être changé: mettez l'application à jour.",
+    ],
+    sessionExpired: [
+        "La session a expiré sur le contrôleur.",
+        "Reconnectez-vous: la reconnexion automatique s'en charge si le mot de passe est connu.",
+    ],
+    backendMissing: [
+        "L'exécutable backend est introuvable.",
+        "Gardez ConnectionInternat-backend.exe à côté de l'application, ou indiquez `backend_path` dans la configuration.",
+    ],
+    spawnFailed: [
+        "Impossible de lancer le backend.",
+        "Vérifiez que le fichier backend est exécutable (chmod +x) et compatible avec ce système.",
+    ],
+    backendStopped: [
+        "Le backend s'est arrêté de manière inattendue.",
+        "Relancez l'application; si le problème persiste, réinstallez-la.",
+    ],
+    backendTimeout: [
+        "Le backend ne répond pas.",
+        "Vérifiez qu'il s'agit bien de ConnectionInternat-backend, puis relancez l'application.",
+    ],
+    incompatibleBackend: [
+        "Le backend ne correspond pas à cette version de l'application.",
+        "Réinstallez l'application pour avoir un backend de la même version.",
+    ],
+    loginCancelled: [
+        "Connexion annulée.",
+        "Choisissez « Essayer de se reconnecter » pour recommencer.",
+    ],
+    unknownError: [
+        "Erreur inattendue.",
+        "Réessayez; si le problème persiste, signalez-le avec le détail ci-dessous.",
+    ],
+
+    backendNotFound: "{} n'existe pas",
+    backendNotExecutable: "{} n'est pas exécutable",
+    backendNotStartable: "Impossible de lancer {}: {}",
+    backendNoPipes: "Impossible d'ouvrir l'entrée et la sortie du backend",
+    versionTimeout: "{} --version n'a pas répondu en {}s",
+    versionUnreadable: "aucune réponse lisible",
+    requestNotWritten: "Impossible d'écrire la requête: {}",
+    backendNoAnswer: "Le backend n'a pas répondu en {}s",
+    backendExited: "Le backend s'est arrêté de manière inattendue",
+    backendThreadStopped: "Le thread du backend s'est arrêté",
+    noOpenSession: "Aucune session ouverte",
+    loginWithoutDigest: "Réponse de connexion sans digest",
+    mockError: "Erreur simulée ({})",
+    mockInvalidScript: "Scénario du backend simulé invalide: {}",
+    mockInvalidDelay: "délai invalide: {}",
+    mockInvalidResult: "résultat invalide: {}",
+    mockMissingResult: "action sans résultat: {}",
+    mockUnknownAction: "action inconnue: {}",
+
+    usage: "\
+Usage: ConnectionInternat [OPTIONS] [COMMANDE]
+
+Commandes:
+  (aucune)             Lance l'interface
+  connect              Se connecte sans interface et garde la session active (ou --daemon)
+  disconnect           Ferme la session de cet appareil sur le contrôleur
+  status               Affiche la configuration et l'état du programme
+  set-credentials      Enregistre l'identifiant et le mot de passe
+  forget-credentials   Oublie les identifiants sauvegardés
+  set-passphrase       Chiffre le mot de passe sauvegardé avec une phrase de passe (vide: en clair)
+  control <ORDRE>      Interroge l'instance en cours: status, reconnect, ping-now, disconnect
+
+Options:
+  --config <FICHIER>   Fichier de configuration (défaut: ~/.internat-connection/config.toml)
+  --backend <CHEMIN>   Exécutable backend (défaut: $BACKEND_PATH, la configuration, ou à côté
+                       du programme, puis dans le dossier courant)
+  --interval <SEC>     Intervalle entre deux pings, en secondes (défaut: la configuration, ou 50)
+  --username <NOM>     Identifiant à utiliser à la place de celui sauvegardé
+  --socket <CHEMIN>    Socket de contrôle (défaut: $CONTROL_SOCKET, ou control.sock à côté de
+                       la configuration)
+  --json               Réponse de `control` en JSON, sur une ligne
+  -V, --version        Affiche la version
+  -h, --help           Affiche cette aide
+",
+    optionNeedsValue: "L'option {} attend une valeur",
+    invalidInterval: "Intervalle invalide: {}",
+    controlNeedsOrder: "La commande control attend un ordre",
+    unknownOrder: "Ordre inconnu: {}",
+    unknownArgument: "Argument inconnu: {}",
+    oneCommandOnly: "Une seule commande à la fois",
+    noSavedUsername: "Aucun identifiant sauvegardé: utilisez `set-credentials` ou lancez l'interface",
+    noSavedPassword: "Aucun mot de passe sauvegardé pour {}",
+    usernamePrompt: "Identifiant: ",
+    passwordPrompt: "Mot de passe de {}: ",
+    emptyUsername: "Identifiant vide",
+    cancelled: "Annulé",
+    pendingUpdate: "Mise à jour de la configuration en attente depuis la version {}, appliquée au prochain lancement",
+    savedUsername: "Identifiant sauvegardé: {} ({})",
+    noSavedUsernameStatus: "Identifiant sauvegardé: aucun",
+    passwordEncrypted: "mot de passe chiffré",
+    passwordInKeyring: "mot de passe dans le trousseau",
+    withoutPassword: "sans mot de passe",
+    withPassword: "avec mot de passe",
+    savedSession: "Session sauvegardée: {} (ouverte le {})",
+    noSavedSession: "Session sauvegardée: aucune",
+    noPendingLogouts: "Sessions à fermer: aucune",
+    pendingLogoutsHeader: "Sessions à fermer (déconnexion en échec):",
+    pendingLogoutSince: "  {} (depuis le {})",
+    eventLogPath: "Journal des événements: {}",
+    controlSocket: "Socket de contrôle: {}",
+    pingInterval: "Intervalle de ping: {}s",
+    controller: "Contrôleur: {}",
+    connectOnStart: "Connexion au lancement: {}",
+    yes: "oui",
+    no: "non",
+    sessionUnavailable: "Impossible d'obtenir la session: {}",
+    sessionClosed: "Session fermée",
+    logoutFailed: "Échec de la déconnexion: {}",
+    logoutTimedOut: "La déconnexion n'a pas abouti à temps",
+    credentialsSaved: "Identifiants sauvegardés dans {}",
+    passwordStoredInKeyring: "Mot de passe enregistré dans le trousseau du système",
+    credentialsForgotten: "Identifiants oubliés",
+    nothingToEncrypt: "Aucun mot de passe sauvegardé à chiffrer",
+    newPassphrasePrompt: "Nouvelle phrase de passe (vide: mot de passe en clair): ",
+    confirmPassphrasePrompt: "Confirmez la phrase de passe: ",
+    passphraseMismatch: "Les deux phrases de passe sont différentes",
+    passwordSealed: "Mot de passe chiffré",
+    passwordInClear: "Mot de passe enregistré en clair",
+    passphrasePrompt: "Phrase de passe: ",
+    passphraseRequired: "Mot de passe chiffré: donnez la phrase de passe dans {}",
+    attachHint: "Pilotez-la avec `control reconnect`, `control disconnect`...",
+    attachUnreachable: "Fermez-la avant d'en lancer une nouvelle",
+    replyStatus: "État: {}",
+    replyUsername: "Identifiant",
+    replyLastLogin: "Dernière connexion",
+    replyLastPing: "Dernier ping réussi",
+    replyLastError: "Dernière erreur: {}",
+
+    instanceOwner: "{} (pid {}, lancée le {})",
+    instanceRunning: "Une autre instance est déjà lancée: {}",
+    instanceRunningUnknown: "Une autre instance est déjà lancée",
+    lockFailed: "Impossible de verrouiller {}: {}",
+    staleLock: "Verrou repris, laissé par une instance arrêtée brutalement: {}",
+
+    logRetryingLogouts: "{} session(s) restée(s) ouverte(s), nouvel essai de déconnexion",
+    logResuming: "Reprise de la session de {} ouverte le {}...",
+    logConnecting: "Connexion en tant que {}...",
+    logControlDisconnect: "Déconnexion demandée par le socket de contrôle",
+    logGivingUp: "Abandon: aucune nouvelle tentative n'est prévue",
+    logStopRequested: "Arrêt demandé",
+    logLogoutTimedOut: "La déconnexion n'a pas abouti à temps, nouvel essai au prochain lancement",
+    logLoggedIn: "Connecté en tant que {}",
+    logLoginFailed: "Échec de la connexion: {}",
+    logResumed: "Session de {} reprise",
+    logResumeFailed: "Session sauvegardée inutilisable, nouvelle connexion: {}",
+    logPingFailed: "Échec du ping, déconnecté: {}",
+    logReconnectScheduled: "Tentative de reconnexion n°{} dans {}s",
+    logReconnecting: "Reconnexion (tentative n°{})...",
+    logLoggedOut: "Déconnecté",
+    logLogoutFailed: "Échec de la déconnexion, nouvel essai plus tard: {}",
+    logDeferredLogoutClosed: "Session de {} restée ouverte: fermée",
+    logDeferredLogoutFailed: "La session de {} reste à fermer: {}",
+
+    configIoError: "[ERREUR]: Impossible d'accéder à la configuration {}: {}",
+    configSyntaxError: "[ERREUR]: Configuration invalide, {} ligne {}: {}",
+    configVaultError: "[ERREUR]: Impossible de déchiffrer le mot de passe ({}): {}",
+    configLayoutError: "[ERREUR]: Configuration {} non reconnue (format {} attendu): {}. Le fichier n'a pas été modifié.",
+    vaultMalformed: "Mot de passe chiffré illisible",
+    vaultWrongPassphrase: "Phrase de passe incorrecte",
+    migrationSchema: "Version du format ajoutée à la configuration, identifiants conservés",
+    migrationKeyValue: "Configuration convertie au format `clé = valeur`, identifiants conservés",
+    configMoved: "Configuration déplacée de {} vers {}",
+    configNewer: "Configuration écrite par une version plus récente (format {}), lue au format {}",
+    configBackedUp: "Ancienne configuration sauvegardée dans {}",
+    layoutSchemaHeader: "`schema=2` attendu en première ligne",
+    layoutLineCount: "{} lignes trouvées, 1 à 3 attendues (version, identifiant, mot de passe)",
+    layoutVersion: "numéro de version attendu, trouvé `{}`",
+    syntaxSection: "les sections [...] ne sont pas prises en charge",
+    syntaxKeyValue: "`clé = valeur` attendu, trouvé `{}`",
+    syntaxKeyName: "nom de clé invalide: `{}`",
+    syntaxMissingValue: "valeur manquante pour `{}`",
+    syntaxInvalidValue: "valeur invalide pour `{}`: `{}` (texte entre guillemets, nombre ou true/false attendu)",
+    syntaxTrailing: "texte inattendu après la valeur: `{}`",
+    syntaxEscape: "échappement inconnu: `\\{}`",
+    syntaxQuote: "guillemet fermant {} manquant",
+    syntaxDuplicate: "`{}` est déjà défini ligne {}",
+    syntaxExpected: "`{}` doit être {}",
+    syntaxExpectedFound: "`{}` doit être {}, pas {}",
+    kindText: "un texte",
+    kindNumber: "un nombre",
+    kindBoolean: "true ou false",
+    kindQuotedText: "un texte entre guillemets",
+    kindPositiveNumber: "un nombre entier positif",
+
+    keyringPasswordMissing: "mot de passe introuvable dans le trousseau",
+    keyringKeptInFile: "mot de passe gardé dans le fichier ({})",
+    keyringUnreadable: "mot de passe illisible dans le trousseau",
+    keyringUnavailable: "aucun trousseau disponible",
+    keyringLocked: "trousseau verrouillé",
+    keyringUnsupported: "trousseau non pris en charge sur ce système",
+
+    controlUnavailable: "Socket de contrôle indisponible: {}",
+    controlUnknownCommand: "Commande inconnue: {}",
+    controlBusy: "Une requête est déjà en cours",
+    controlNoCredentials: "Aucun identifiant à utiliser pour se reconnecter",
+    controlNoSession: "Aucune session ouverte",
+    controlAlreadyListening: "une autre instance écoute déjà sur {}",
+    controlNoInstance: "Aucune instance en cours sur {} ({})",
+    controlInvalidReply: "Réponse invalide: {}",
+    controlUnsupported: "socket de contrôle indisponible sur ce système",
+
+    crashReportWritten: "Rapport de plantage: {}",
+    crashReportFailed: "Impossible d'écrire le rapport de plantage: {}",
+    crashStateNotSaved: "Impossible d'enregistrer l'état de la session: {}",
+    crashNoSession: "aucune session ouverte",
+    crashClosingSession: "Fermeture de la session de {}...",
+    crashSessionClosed: "session de {} fermée",
+    crashSessionStillSaved: "session de {} fermée, mais toujours sauvegardée: {}",
+    crashLogoutDeferred:
+        "échec de la fermeture de la session de {}, nouvel essai au prochain lancement: {}",
+    crashLogoutLost: "échec de la fermeture de la session de {} ({}), impossible de la garder pour \
+                      le prochain lancement: {}",
+    crashNoAnswer: "pas de réponse en {}s",
+    crashReport: "ConnectionInternat {}\nDate: {}\nPanique: {}\nDéconnexion: {}\n\n\
+                  Derniers événements:\n{}\nPile d'appels:\n{}\n",
+};
+
+pub const ENGLISH: Catalog = Catalog {
+    menuTitle: "Actions",
+    connect: "Log in",
+    connectAs: "Log in (as {})",
+    showStatus: "Show the current connection",
+    enterCredentials: "Enter credentials",
+    forgetCredentials: "Forget saved credentials",
+    quit: "Quit",
+    cancel: "Cancel",
+    reconnect: "Try to reconnect",
+    disconnect: "Log out",
+    disconnectAndQuit: "Log out and quit",
+    checkAgain: "Check again",
+    whatToDo: "What do you want to do?",
+
+    usernameLabel: "Username",
+    passwordLabel: "Password",
+    passphraseLabel: "Passphrase",
+    revealHint: "F2: show",
+    hideHint: "F2: hide",
+
+    unlockHelp: [
+        "The saved password is encrypted.",
+        "Enter the passphrase to unlock it (Esc to quit)",
+    ],
+    updatedTo: "Updated to version {}",
+    updatedFrom: "Updated: {} → {}",
+    keyringNotice: "Keyring: {}",
+    signalHandlerFailed: "Signals not handled ({}): stopping by signal will leave the session open",
+    confirmQuit: "You are logged in as {}. The session will be closed before quitting.",
+    disconnecting: "Logging out...",
+    searchedLocations: "Searched locations:",
+    originOption: "--backend option",
+    originEnvironment: "BACKEND_PATH variable",
+    originConfig: "backend_path in the configuration",
+    originNextToProgram: "next to the program",
+    originWorkingDirectory: "current directory",
+
+    footerHome: " Use the ↓↑ arrows to move through the menu, Enter to confirm. ",
+    footerUsername: " Type your username. Press ",
+    footerPassword: " Type your password. Press ",
+    footerNextStep: " to go to the next step ",
+    enterKey: "Enter",
+    footerStatus: " Esc: back to the home screen, the connection stays open ",
+    footerBackend: " Esc: quit ",
+
+    statusLine: "Status: {}",
+    lastLogin: "Last login: {}",
+    lastPing: "Last ping: {}",
+    lastPingAgo: "{} ({} seconds ago)",
+    lastPingRetry: "{} ({}s ago) (attempt {}s ago)",
+    backendLine: "Backend {}: {}",
+    backendNotStarted: "{} (not started yet)",
+    mockSessions: "{} simulated session(s)",
+    notAvailable: "N/A",
+    statusUninitialized: "Not initialized",
+    statusConnected: "Connected",
+    statusDisconnected: "Disconnected",
+    statusConnecting: "Connecting... {} ({}s)",
+    retryIn: "Automatic reconnection: attempt {} in {}s",
+    retry: "Automatic reconnection: attempt {}",
+    error: "Error: {}",
+    pendingLogouts: "Sessions left open, closed as soon as the controller answers:",
+    pendingLogout: "{} (logout failing since {})",
+    stateNotSaved: "Could not write {}: {}",
+    eventLogFailed: "Could not write the event log {}: {}",
+
+    errorDetail: "Detail: {}",
+    invalidRequest: [
+        "The backend rejected the request.",
+        "Check that the backend has the same version as the application.",
+    ],
+    networkFailure: [
+        "The controller cannot be reached.",
+        "Check that you are connected to the boarding school network (cable or Wi-Fi).",
+    ],
+    dnsFailure: [
+        "DNS resolution failed: the controller address is unknown.",
+        "Check that you are connected to the right network and that no VPN is running.",
+    ],
+    connectionRefused: [
+        "The controller refused the connection.",
+        "Check `portal_url` in the configuration, or try again in a few minutes.",
+    ],
+    timeout: [
+        "The controller did not answer in time.",
+        "The network may be congested: try again in a moment.",
+    ],
+    tlsFailure: [
+        "The secure connection to the controller failed.",
+        "Check `portal_url` and that no proxy intercepts the connection.",
+    ],
+    httpStatus: [
+        "The controller answered with HTTP status {}.",
+        "The controller probably has a temporary problem: try again later.",
+    ],
+    serverNotFound: [
+        "Cannot find the server.",
+        "Check that you are connected to the right network.",
+    ],
+    invalidCredentials: [
+        "Incorrect username or password.",
+        "Enter your username and password again from the home screen.",
+    ],
+    malformedResponse: [
+        "The controller sent a response that cannot be understood.",
+        "The portal may have changed: update the application.",
+    ],
+    sessionExpired: [
+        "The session expired on the controller.",
+        "Log in again: automatic reconnection does it when the password is known.",
+    ],
+    backendMissing: [
+        "The backend executable cannot be found.",
+        "Keep ConnectionInternat-backend.exe next to the application, or set `backend_path` in the configuration.",
+    ],
+    spawnFailed: [
+        "The backend cannot be started.",
+        "Check that the backend file is executable (chmod +x) and built for this system.",
+    ],
+    backendStopped: [
+        "The backend stopped unexpectedly.",
+        "Restart the application; if the problem persists, reinstall it.",
+    ],
+    backendTimeout: [
+        "The backend does not answer.",
+        "Check that the file really is ConnectionInternat-backend, then restart the application.",
+    ],
+    incompatibleBackend: [
+        "The backend does not match this version of the application.",
+        "Reinstall the application to get a backend of the same version.",
+    ],
+    loginCancelled: [
+        "Login cancelled.",
+        "Choose “Try to reconnect” to start again.",
+    ],
+    unknownError: [
+        "Unexpected error.",
+        "Try again; if the problem persists, report it with the detail below.",
+    ],
+
+    backendNotFound: "{} does not exist",
+    backendNotExecutable: "{} is not executable",
+    backendNotStartable: "Cannot start {}: {}",
+    backendNoPipes: "Cannot open the backend's stdin and stdout",
+    versionTimeout: "{} --version did not answer within {}s",
+    versionUnreadable: "no readable response",
+    requestNotWritten: "Cannot write the request: {}",
+    backendNoAnswer: "The backend did not answer within {}s",
+    backendExited: "The backend stopped unexpectedly",
+    backendThreadStopped: "The backend thread stopped",
+    noOpenSession: "No open session",
+    loginWithoutDigest: "Login response without a digest",
+    mockError: "Simulated error ({})",
+    mockInvalidScript: "Invalid mock backend script: {}",
+    mockInvalidDelay: "invalid delay: {}",
+    mockInvalidResult: "invalid result: {}",
+    mockMissingResult: "action without a result: {}",
+    mockUnknownAction: "unknown action: {}",
+
+    usage: "\
+Usage: ConnectionInternat [OPTIONS] [COMMAND]
+
+Commands:
+  (none)               Start the interface
+  connect              Log in without the interface and keep the session alive (or --daemon)
+  disconnect           Close this device's session on the controller
+  status               Show the configuration and the state of the program
+  set-credentials      Save the username and password
+  forget-credentials   Forget the saved credentials
+  set-passphrase       Encrypt the saved password with a passphrase (empty: in clear)
+  control <ORDER>      Query the running instance: status, reconnect, ping-now, disconnect
+
+Options:
+  --config <FILE>      Configuration file (default: ~/.internat-connection/config.toml)
+  --backend <PATH>     Backend executable (default: $BACKEND_PATH, the configuration, or next to
+                       the program, then in the current directory)
+  --interval <SEC>     Seconds between two pings (default: the configuration, or 50)
+  --username <NAME>    Username to use instead of the saved one
+  --socket <PATH>      Control socket (default: $CONTROL_SOCKET, or control.sock next to the
+                       config)
+  --json               `control` reply as JSON, on one line
+  -V, --version        Show the version
+  -h, --help           Show this help
+",
+    optionNeedsValue: "Option {} needs a value",
+    invalidInterval: "Invalid interval: {}",
+    controlNeedsOrder: "The control command needs an order",
+    unknownOrder: "Unknown order: {}",
+    unknownArgument: "Unknown argument: {}",
+    oneCommandOnly: "Only one command at a time",
+    noSavedUsername: "No saved username: use `set-credentials` or start the interface",
+    noSavedPassword: "No saved password for {}",
+    usernamePrompt: "Username: ",
+    passwordPrompt: "Password for {}: ",
+    emptyUsername: "Empty username",
+    cancelled: "Cancelled",
+    pendingUpdate: "Configuration update pending from version {}, applied on next start",
+    savedUsername: "Saved username: {} ({})",
+    noSavedUsernameStatus: "Saved username: none",
+    passwordEncrypted: "encrypted password",
+    passwordInKeyring: "password in the keyring",
+    withoutPassword: "no password",
+    withPassword: "with password",
+    savedSession: "Saved session: {} (opened on {})",
+    noSavedSession: "Saved session: none",
+    noPendingLogouts: "Sessions to close: none",
+    pendingLogoutsHeader: "Sessions to close (failed logout):",
+    pendingLogoutSince: "  {} (since {})",
+    eventLogPath: "Event log: {}",
+    controlSocket: "Control socket: {}",
+    pingInterval: "Ping interval: {}s",
+    controller: "Controller: {}",
+    connectOnStart: "Connect on start: {}",
+    yes: "yes",
+    no: "no",
+    sessionUnavailable: "Could not get the session: {}",
+    sessionClosed: "Session closed",
+    logoutFailed: "Logout failed: {}",
+    logoutTimedOut: "Logout did not complete in time",
+    credentialsSaved: "Credentials saved in {}",
+    passwordStoredInKeyring: "Password stored in the system keyring",
+    credentialsForgotten: "Credentials forgotten",
+    nothingToEncrypt: "No saved password to encrypt",
+    newPassphrasePrompt: "New passphrase (empty: plain password): ",
+    confirmPassphrasePrompt: "Confirm the passphrase: ",
+    passphraseMismatch: "The two passphrases differ",
+    passwordSealed: "Password encrypted",
+    passwordInClear: "Password saved in clear",
+    passphrasePrompt: "Passphrase: ",
+    passphraseRequired: "Encrypted password: give the passphrase in {}",
+    attachHint: "Drive it with `control reconnect`, `control disconnect`...",
+    attachUnreachable: "Close it before starting a new one",
+    replyStatus: "State: {}",
+    replyUsername: "Username",
+    replyLastLogin: "Last login",
+    replyLastPing: "Last successful ping",
+    replyLastError: "Last error: {}",
+
+    instanceOwner: "{} (pid {}, started on {})",
+    instanceRunning: "Another instance is already running: {}",
+    instanceRunningUnknown: "Another instance is already running",
+    lockFailed: "Could not lock {}: {}",
+    staleLock: "Lock taken over, left by an instance that stopped abruptly: {}",
+
+    logRetryingLogouts: "{} session(s) left open, trying to log out again",
+    logResuming: "Resuming the session of {} opened on {}...",
+    logConnecting: "Logging in as {}...",
+    logControlDisconnect: "Logout requested through the control socket",
+    logGivingUp: "Giving up: no further attempt is planned",
+    logStopRequested: "Stop requested",
+    logLogoutTimedOut: "Logout did not complete in time, trying again on next start",
+    logLoggedIn: "Logged in as {}",
+    logLoginFailed: "Login failed: {}",
+    logResumed: "Session of {} resumed",
+    logResumeFailed: "Saved session unusable, logging in again: {}",
+    logPingFailed: "Ping failed, disconnected: {}",
+    logReconnectScheduled: "Reconnection attempt #{} in {}s",
+    logReconnecting: "Reconnecting (attempt #{})...",
+    logLoggedOut: "Logged out",
+    logLogoutFailed: "Logout failed, trying again later: {}",
+    logDeferredLogoutClosed: "Session of {} left open: closed",
+    logDeferredLogoutFailed: "The session of {} is still to be closed: {}",
+
+    configIoError: "[ERROR]: Could not access the configuration {}: {}",
+    configSyntaxError: "[ERROR]: Invalid configuration, {} line {}: {}",
+    configVaultError: "[ERROR]: Could not decrypt the password ({}): {}",
+    configLayoutError: "[ERROR]: Unrecognized configuration {} (format {} expected): {}. The file was not modified.",
+    vaultMalformed: "Unreadable encrypted password",
+    vaultWrongPassphrase: "Wrong passphrase",
+    migrationSchema: "Format version added to the configuration, credentials kept",
+    migrationKeyValue: "Configuration converted to the `key = value` format, credentials kept",
+    configMoved: "Configuration moved from {} to {}",
+    configNewer: "Configuration written by a newer version (format {}), read as format {}",
+    configBackedUp: "Previous configuration saved in {}",
+    layoutSchemaHeader: "`schema=2` expected on the first line",
+    layoutLineCount: "{} lines found, 1 to 3 expected (version, username, password)",
+    layoutVersion: "version number expected, found `{}`",
+    syntaxSection: "[...] sections are not supported",
+    syntaxKeyValue: "`key = value` expected, found `{}`",
+    syntaxKeyName: "invalid key name: `{}`",
+    syntaxMissingValue: "missing value for `{}`",
+    syntaxInvalidValue: "invalid value for `{}`: `{}` (quoted text, number or true/false expected)",
+    syntaxTrailing: "unexpected text after the value: `{}`",
+    syntaxEscape: "unknown escape: `\\{}`",
+    syntaxQuote: "missing closing quote {}",
+    syntaxDuplicate: "`{}` is already set on line {}",
+    syntaxExpected: "`{}` must be {}",
+    syntaxExpectedFound: "`{}` must be {}, not {}",
+    kindText: "a text",
+    kindNumber: "a number",
+    kindBoolean: "true or false",
+    kindQuotedText: "a quoted text",
+    kindPositiveNumber: "a positive integer",
+
+    keyringPasswordMissing: "password not found in the keyring",
+    keyringKeptInFile: "password kept in the file ({})",
+    keyringUnreadable: "unreadable password in the keyring",
+    keyringUnavailable: "no keyring available",
+    keyringLocked: "keyring locked",
+    keyringUnsupported: "keyring not supported on this system",
+
+    controlUnavailable: "Control socket unavailable: {}",
+    controlUnknownCommand: "Unknown command: {}",
+    controlBusy: "A request is already in progress",
+    controlNoCredentials: "No credentials to reconnect with",
+    controlNoSession: "No open session",
+    controlAlreadyListening: "another instance is already listening on {}",
+    controlNoInstance: "No running instance on {} ({})",
+    controlInvalidReply: "Invalid reply: {}",
+    controlUnsupported: "control socket not available on this system",
+
+    crashReportWritten: "Crash report: {}",
+    crashReportFailed: "Could not write the crash report: {}",
+    crashStateNotSaved: "Could not save the session state: {}",
+    crashNoSession: "no open session",
+    crashClosingSession: "Closing the session of {}...",
+    crashSessionClosed: "session of {} closed",
+    crashSessionStillSaved: "session of {} closed, but still saved: {}",
+    crashLogoutDeferred: "failed to close the session of {}, retrying on next start: {}",
+    crashLogoutLost:
+        "failed to close the session of {} ({}), and could not keep it for next start: {}",
+    crashNoAnswer: "no answer in {}s",
+    crashReport: "ConnectionInternat {}\nDate: {}\nPanic: {}\nLogout: {}\n\nRecent events:\n{}\n\
+                  Backtrace:\n{}\n",
+};
@@ -0,0 +1,115 @@
+use chrono::Local;
+use miniserde::{json, Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, TryLockError};
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::i18n::{fill, tr};
+use crate::DATE_FORMAT;
+
+const LOCK_FILE_NAME: &str = "instance.lock";
+
+// Une seule instance (interface ou `connect`) par dossier de configuration: deux instances
+// ouvriraient chacune leur session, et la première à quitter fermerait celle de l'autre.
+// Le verrou est consultatif et tenu par le système tant que le fichier reste ouvert: il disparaît
+// avec le processus, même arrêté brutalement. Le fichier garde qui le tient, pour le message de la
+// seconde instance; non vide alors que personne ne le tient, il a été laissé par un plantage.
+pub struct InstanceLock {
+    file: File,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Owner {
+    pub pid: u32,
+    // "interface", "connect" ou "disconnect"
+    pub mode: String,
+    pub since: String,
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            fill(tr().instanceOwner, &[&self.mode, &self.pid, &self.since])
+        )
+    }
+}
+
+pub enum LockError {
+    // une autre instance tient le verrou; `None` si le fichier est illisible
+    Held(Option<Owner>),
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockError::Held(Some(owner)) => write!(f, "{}", fill(tr().instanceRunning, &[owner])),
+            LockError::Held(None) => write!(f, "{}", tr().instanceRunningUnknown),
+            LockError::Io(path, err) => {
+                write!(f, "{}", fill(tr().lockFailed, &[&path.display(), err]))
+            }
+        }
+    }
+}
+
+pub fn path(configDir: &Path) -> PathBuf {
+    configDir.join(LOCK_FILE_NAME)
+}
+
+// Prend le verrou pour cette instance; renvoie aussi l'instance qui l'avait laissé sans le libérer
+pub fn acquire(configDir: &Path, mode: &str) -> Result<(InstanceLock, Option<Owner>), LockError> {
+    let path = path(configDir);
+    let io_error = |err| LockError::Io(path.clone(), err);
+
+    fs::create_dir_all(configDir).map_err(io_error)?;
+    // jamais supprimé: un autre processus pourrait verrouiller le fichier retiré pendant qu'un
+    // troisième en crée un nouveau
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(io_error)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Err(LockError::Held(read_owner(&path))),
+        Err(TryLockError::Error(err)) => return Err(io_error(err)),
+    }
+
+    let stale = read_owner(&path);
+    let owner = Owner {
+        pid: std::process::id(),
+        mode: mode.to_string(),
+        since: Local::now().format(DATE_FORMAT).to_string(),
+    };
+    file.set_len(0).map_err(io_error)?;
+    file.rewind().map_err(io_error)?;
+    file.write_all(format!("{}\n", json::to_string(&owner)).as_bytes())
+        .map_err(io_error)?;
+    Ok((InstanceLock { file }, stale))
+}
+
+pub fn stale_message(owner: &Owner) -> String {
+    fill(tr().staleLock, &[owner])
+}
+
+// Sous Windows, le verrou empêche aussi de lire le fichier: l'instance reste alors anonyme
+fn read_owner(path: &Path) -> Option<Owner> {
+    let content = fs::read_to_string(path).ok()?;
+    json::from_str(content.trim()).ok()
+}
+
+impl Drop for InstanceLock {
+    // arrêt normal: le fichier vidé n'est pas pris pour un verrou laissé par un plantage. Les
+    // échecs sont sans suite: au pire, le lancement suivant signale un verrou laissé, et le système
+    // libère le verrou à la fermeture du fichier.
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
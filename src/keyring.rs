@@ -0,0 +1,105 @@
+// Mot de passe dans le trousseau du système (API Secret Service de freedesktop, sous Linux: GNOME
+// Keyring, KWallet...) plutôt que dans le fichier de configuration. Sans trousseau disponible, les
+// fonctions renvoient une erreur et la configuration garde le mot de passe dans le fichier.
+//
+// Une entrée par identifiant, reconnue par ses attributs `application` et `username`.
+
+#[cfg(target_os = "linux")]
+const APPLICATION: &str = "ConnectionInternat";
+// Ailleurs, le mot de passe reste dans le fichier sans le signaler
+pub const SUPPORTED: bool = cfg!(target_os = "linux");
+
+#[cfg(target_os = "linux")]
+mod secret_service {
+    use secret_service::blocking::{Collection, SecretService};
+    use secret_service::{EncryptionType, Error};
+    use std::collections::HashMap;
+
+    use super::APPLICATION;
+    use crate::i18n::tr;
+
+    fn attributes(username: &str) -> HashMap<&str, &str> {
+        HashMap::from([("application", APPLICATION), ("username", username)])
+    }
+
+    // Le chiffrement de la session protège le secret sur le bus: un service qui ne connaît que
+    // l'échange en clair est traité comme un trousseau indisponible
+    fn connect() -> Result<SecretService<'static>, Error> {
+        SecretService::connect(EncryptionType::Dh)
+    }
+
+    fn default_collection<'a>(service: &'a SecretService) -> Result<Collection<'a>, Error> {
+        let collection = service.get_default_collection()?;
+        collection.ensure_unlocked()?;
+        Ok(collection)
+    }
+
+    pub fn store(username: &str, password: &str) -> Result<(), String> {
+        let service = connect().map_err(describe)?;
+        let collection = default_collection(&service).map_err(describe)?;
+        collection
+            .create_item(
+                &format!("{} ({})", APPLICATION, username),
+                attributes(username),
+                password.as_bytes(),
+                true,
+                "text/plain",
+            )
+            .map(|_| ())
+            .map_err(describe)
+    }
+
+    pub fn load(username: &str) -> Result<Option<String>, String> {
+        let service = connect().map_err(describe)?;
+        let items = service
+            .search_items(attributes(username))
+            .map_err(describe)?;
+
+        let item = match items.unlocked.first().or(items.locked.first()) {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        item.ensure_unlocked().map_err(describe)?;
+        let secret = item.get_secret().map_err(describe)?;
+        String::from_utf8(secret)
+            .map(Some)
+            .map_err(|_| tr().keyringUnreadable.to_string())
+    }
+
+    pub fn delete(username: &str) -> Result<(), String> {
+        let service = connect().map_err(describe)?;
+        let items = service
+            .search_items(attributes(username))
+            .map_err(describe)?;
+        for item in items.unlocked.iter().chain(items.locked.iter()) {
+            item.delete().map_err(describe)?;
+        }
+        Ok(())
+    }
+
+    fn describe(error: Error) -> String {
+        match error {
+            Error::Unavailable => tr().keyringUnavailable.to_string(),
+            Error::Locked | Error::Prompt => tr().keyringLocked.to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use self::secret_service::{delete, load, store};
+
+#[cfg(not(target_os = "linux"))]
+pub fn store(_username: &str, _password: &str) -> Result<(), String> {
+    Err(crate::i18n::tr().keyringUnsupported.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn load(_username: &str) -> Result<Option<String>, String> {
+    Err(crate::i18n::tr().keyringUnsupported.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn delete(_username: &str) -> Result<(), String> {
+    Ok(())
+}
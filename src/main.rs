@@ -1,35 +1,50 @@
-#![allow(warnings)]
-use chrono::{Date, DateTime, Local, Utc};
-use core::panic;
-use std::borrow::Borrow;
-use std::fmt::format;
-use std::io::{self, BufReader, Read};
-use std::io::{BufRead, Write};
-use std::process::{Command, Stdio};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use wait_timeout::ChildExt;
+#![allow(non_snake_case)]
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use cli::Cli;
 use config::Config;
+use control::ControlServer;
 use ratatui::{
-    buffer::Buffer,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
-    layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
-    style::{Color, Modifier, Style, Stylize},
-    symbols,
-    text::{Line, Span, Text},
-    widgets::{
-        Block, Borders, HighlightSpacing, List, ListDirection, ListItem, ListState, Paragraph,
-        StatefulWidget, Widget, Wrap,
+    crossterm::event::{
+        self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers,
     },
+    crossterm::execute,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Borders, Paragraph, Wrap},
     DefaultTerminal, Frame,
 };
 
 use tui_big_text::{BigText, PixelSize};
 
+mod backend;
+mod backoff;
+mod cli;
 mod components;
 mod config;
-
-use components::{Input, InputMode, Menu, Offset};
+mod connection;
+mod control;
+mod crash;
+mod daemon;
+mod eventlog;
+mod i18n;
+mod instance;
+mod keyring;
+mod mock;
+mod protocol;
+mod session;
+mod vault;
+
+use components::{Input, InputMode, Menu, MenuItem};
+use connection::{
+    describe_error, explain_error, seconds_since, Connection, ConnectionEvent, ConnectionStatus,
+};
+use i18n::{fill, tr};
 //use reqwest::Result;
 
 enum Step {
@@ -37,39 +52,80 @@ enum Step {
     Password,
 }
 
+// Actions des menus
+#[derive(Clone, Copy, PartialEq)]
+enum HomeAction {
+    Connect,
+    ShowStatus,
+    EnterCredentials,
+    ForgetCredentials,
+    Quit,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum StatusAction {
+    Reconnect,
+    Disconnect,
+    Cancel,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ConfirmAction {
+    DisconnectAndQuit,
+    Cancel,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BackendAction {
+    CheckAgain,
+    Quit,
+}
+
 #[derive(PartialEq)]
 enum Screen {
+    // Backend absent ou inutilisable, vérifié au lancement
+    BackendError,
+    Unlock,
     Home,
     Credentials,
     Status,
+    // Quitter avec une session ouverte
+    ConfirmQuit,
     Disconnect,
 
     Exit,
 }
 
-#[derive(PartialEq)]
-enum ConnectionStatus {
-    Uninitialized,
-    Connected,
-    Disconnected,
-    Connecting,
-}
-
 struct App {
     // Paramètres généraux
     config: Config,
+    // Dernière erreur de sauvegarde de la configuration
+    configError: Option<String>,
 
     // Paramètres de l'application
     screen: Screen,
-    username: Option<String>,
-    password: Option<String>,
-    passwordDigest: Option<String>,
-    connectionStatus: ConnectionStatus,
-    lastLogin: Option<String>,
-    lastPingAttempt: Option<DateTime<Local>>,
-    lastPingTimestamp: Option<DateTime<Local>>,
+    // Écrans précédents, pour revenir en arrière; vide à la racine (accueil ou déverrouillage)
+    history: Vec<Screen>,
+    // La déconnexion en cours a été demandée pour quitter le programme
+    quitting: bool,
+    connection: Connection,
     backendPath: String,
-    lastError: Option<String>,
+    // Emplacements où le backend est cherché, et le problème trouvé au lancement
+    backendLocations: Vec<backend::Location>,
+    backendError: Option<protocol::ResponseError>,
+    // Socket de contrôle, si l'adresse est libre; sinon, pourquoi
+    control: Option<ControlServer>,
+    controlError: Option<String>,
+    // SIGINT, SIGTERM ou SIGHUP reçu: la session est fermée avant de quitter
+    interrupted: Arc<AtomicBool>,
+    // la boucle a vu le signal et se charge de la déconnexion
+    interruptHandled: Arc<AtomicBool>,
+    // les signaux ne sont pas interceptés: les recevoir laisse la session ouverte
+    signalError: Option<String>,
+
+    // Phrase de passe du coffre, demandée au lancement quand le mot de passe est chiffré
+    passphrase_component: Input,
+    unlockError: Option<String>,
 
     // Paramètre de l'entrée des identifiants
     step: Step,
@@ -77,87 +133,216 @@ struct App {
     password_component: Input,
 
     // Paramètre de l'écran d'accueil
-    menu: Menu,
+    menu: Menu<HomeAction>,
     // first element is the last connection status where this was updated: if it's different from current status, it probably needs to be changed
-    status_menu: Menu,
-
-    value: String,
+    status_menu: Menu<StatusAction>,
+    confirm_menu: Menu<ConfirmAction>,
+    backend_menu: Menu<BackendAction>,
 }
 
 const TICK_RATE: u64 = 1000;
+// Délai laissé à la boucle de l'interface pour réagir à un signal d'arrêt
+const SIGNAL_GRACE: Duration = Duration::from_secs(1);
+// Délai maximal entre deux rafraîchissements de l'écran, pour animer l'indicateur de connexion
+const FRAME_RATE: u64 = 100;
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 const PING_INTERVAL: i64 = 50;
 const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// Nom du backend, cherché à côté du programme puis dans le dossier courant
 const BACKEND_FILE_NAME: &str = "ConnectionInternat-backend.exe";
-
-fn getBackendPath() -> String {
-    // if env variable BACKEND_PATH is set, use it
-    if let Ok(path) = std::env::var("BACKEND_PATH") {
-        return path;
-    }
-    let mut path = std::env::current_exe().unwrap();
-    if !path.pop() {
-        panic!("Failed to pop path to backend");
-    }
-    path.push(BACKEND_FILE_NAME);
-    path.to_str().unwrap().to_string()
+// Délai avant la première tentative de reconnexion automatique, puis délai maximal par défaut
+const RECONNECT_BASE_DELAY: u64 = 5;
+const RECONNECT_MAX_DELAY: u64 = 300;
+
+fn getReconnectMaxDelay(configured: Option<u64>) -> u64 {
+    // if env variable RECONNECT_MAX_DELAY is set (in seconds), use it
+    std::env::var("RECONNECT_MAX_DELAY")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .or(configured)
+        .unwrap_or(RECONNECT_MAX_DELAY)
 }
 
 impl App {
-    fn new() -> Self {
-        let config = Config::init();
-        config.save();
-
-        let home_menu = Menu::new(
-            "Actions",
-            if config.password != "" && config.username != "" {
-                vec![
-                    format!("Se connecter (en tant que {})", config.username).to_string(),
-                    "Rentrer ses identifiants".to_string(),
-                    "Oublier les identifiants sauvegardés".to_string(),
-                    "Quitter".to_string(),
-                ]
-            } else {
-                vec![
-                    "Rentrer ses identifiants".to_string(),
-                    "Quitter".to_string(),
-                ]
-            },
-        );
+    fn new(cli: &Cli, config: Config) -> Self {
+        let backendLocations = cli.backend_locations(&config);
+        let backendPath = backend::choose(&backendLocations);
+        let connection = cli.connection(&config);
+
+        let home_menu = Menu::new(tr().menuTitle, home_items(&config, false));
+
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let interruptHandled = Arc::new(AtomicBool::new(false));
+        let (handler_interrupted, handler_handled) =
+            (interrupted.clone(), interruptHandled.clone());
+        let signalError = ctrlc::set_handler(move || {
+            handler_interrupted.store(true, Ordering::SeqCst);
+            // la boucle de l'interface reste bloquée sur un terminal disparu (SIGHUP): sans
+            // nouvelles d'elle, la session est fermée depuis ce thread
+            std::thread::sleep(SIGNAL_GRACE);
+            if !handler_handled.load(Ordering::SeqCst) {
+                crash::logout_now();
+                std::process::exit(0);
+            }
+        })
+        .err()
+        .map(|err| fill(tr().signalHandlerFailed, &[&err]));
 
-        Self {
+        let (control, controlError) = match ControlServer::bind(cli.socket_path()) {
+            Ok(control) => (Some(control), None),
+            Err(err) => (None, Some(fill(tr().controlUnavailable, &[&err]))),
+        };
+
+        let mut app = Self {
             config,
+            configError: None,
 
             screen: Screen::Home,
-            username: None,
-            password: None,
-            passwordDigest: None,
-            connectionStatus: ConnectionStatus::Uninitialized,
-            lastLogin: None,
-            lastPingAttempt: None,
-            lastPingTimestamp: None,
-            backendPath: getBackendPath(),
-            lastError: None,
+            history: Vec::new(),
+            quitting: false,
+            connection,
+            backendPath,
+            backendLocations,
+            backendError: None,
+            control,
+            controlError,
+            interrupted,
+            interruptHandled,
+            signalError,
+
+            passphrase_component: Input::secret(tr().passphraseLabel, true),
+            unlockError: None,
 
             step: Step::Username,
-            username_component: Input::new("Identifiant", true),
-            password_component: Input::new("Mot de passe", false),
+            username_component: {
+                let mut input = Input::new(tr().usernameLabel, true);
+                if let Some(username) = &cli.username {
+                    input.set_value(username.clone());
+                }
+                input
+            },
+            password_component: Input::secret(tr().passwordLabel, false),
 
             menu: home_menu,
-            status_menu: Menu::new("Actions", vec!["Se déconnecter".to_string()]),
+            status_menu: Menu::new(
+                tr().menuTitle,
+                status_items(ConnectionStatus::Uninitialized),
+            ),
+            confirm_menu: confirm_menu(),
+            backend_menu: Menu::new(tr().menuTitle, backend_items()),
+        };
+
+        app.launch();
+        app
+    }
 
-            value: String::new(),
+    // Vérifie le backend, puis demande la phrase de passe si le mot de passe est chiffré
+    fn launch(&mut self) {
+        self.history.clear();
+        if let Err(err) = backend::check(&self.backendPath) {
+            self.backendError = Some(err);
+            self.screen = Screen::BackendError;
+            return;
+        }
+        self.backendError = None;
+
+        if self.config.is_locked() {
+            self.screen = Screen::Unlock;
+        } else {
+            self.start();
+        }
+    }
+
+    // Le backend a peut-être été installé ou déplacé entre temps: on le cherche à nouveau
+    fn check_backend_again(&mut self) {
+        let path = backend::choose(&self.backendLocations);
+        if path != self.backendPath {
+            let pingInterval = self.connection.pingInterval;
+            self.connection = Connection::new(
+                backend::from_path(&path, self.config.portalUrl.clone()),
+                Duration::from_secs(getReconnectMaxDelay(self.config.reconnectMaxDelay)),
+            );
+            self.connection.pingInterval = pingInterval;
+            self.connection.keep_state_in(self.config.dir());
+            crash::set_backend_path(&path);
+            self.backendPath = path;
+        }
+        self.launch();
+    }
+
+    // Première action, une fois la configuration lisible
+    fn start(&mut self) {
+        // l'accueil devient la racine: le déverrouillage n'est plus accessible
+        self.history.clear();
+        self.screen = Screen::Home;
+        self.refresh_home_menu();
+
+        // une session restée ouverte au lancement précédent est reprise d'office; sinon
+        // `auto_connect = true` dans la configuration: on se connecte sans passer par le menu
+        let resumable = self
+            .connection
+            .saved_session(&self.config.username)
+            .is_some();
+        let autoConnect = self.config.autoConnect
+            && !self.config.username.is_empty()
+            && !self.config.password.is_empty();
+        // sessions restées ouvertes au lancement précédent: fermées avant d'en ouvrir une, le
+        // contrôleur pouvant redonner le même digest
+        self.connection.retry_logouts();
+        if resumable || autoConnect {
+            self.connect();
+        }
+    }
+
+    // Reprend la session sauvegardée, ou se connecte avec les identifiants de la configuration
+    fn connect(&mut self) {
+        let password = Some(self.config.password.clone()).filter(|password| !password.is_empty());
+        self.connection.open(self.config.username.clone(), password);
+        self.refresh_status_menu();
+        self.open(Screen::Status);
+    }
+
+    fn unlock(&mut self) {
+        let passphrase = self.passphrase_component.value.clone();
+        match self.config.unlock(&passphrase) {
+            Ok(()) => {
+                self.unlockError = None;
+                self.start();
+            }
+            Err(err) => {
+                self.unlockError = Some(err.to_string());
+                self.passphrase_component.submit_input();
+                self.passphrase_component.mode = InputMode::Editing;
+            }
         }
     }
 
+    fn save_config(&mut self) {
+        self.configError = self.config.save().err().map(|err| err.to_string());
+    }
+
     fn on_tick(&mut self) {
-        if !matches!(self.connectionStatus, ConnectionStatus::Uninitialized)
-            && !self.passwordDigest.is_none()
-        {
-            let seconds = seconds_since(self.lastPingAttempt).unwrap_or(0);
-            if seconds >= PING_INTERVAL {
-                self.ping()
+        self.connection.tick();
+    }
+
+    fn handle_connection_event(&mut self, event: ConnectionEvent) {
+        match event {
+            ConnectionEvent::LoggedIn => {
+                self.config.username = self.connection.username.clone().unwrap();
+                self.config.password = self.connection.password.clone().unwrap();
+                self.save_config();
             }
+            ConnectionEvent::LoggedOut(result) if self.screen == Screen::Disconnect => {
+                self.after_disconnect();
+                // la session reste à fermer: le statut l'indique, avec l'erreur
+                if result.is_err() && self.screen == Screen::Home {
+                    self.open(Screen::Status);
+                }
+            }
+            _ => {}
         }
+        self.refresh_status_menu();
+        self.refresh_home_menu();
     }
 
     fn draw_credentials(&mut self, frame: &mut Frame, area: Rect) {
@@ -168,13 +353,71 @@ impl App {
         self.password_component.draw(frame, password_area);
     }
 
+    fn draw_unlock(&mut self, frame: &mut Frame, area: Rect) {
+        let [help_area, input_area, error_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(3),
+            Constraint::Min(1),
+        ])
+        .areas(area);
+
+        frame.render_widget(
+            Paragraph::new(tr().unlockHelp.map(Line::from).to_vec()),
+            help_area,
+        );
+        self.passphrase_component.draw(frame, input_area);
+        if let Some(err) = &self.unlockError {
+            frame.render_widget(Paragraph::new(err.as_str()).red(), error_area);
+        }
+    }
+
     fn draw_home(&mut self, frame: &mut Frame, area: Rect) {
-        let [help_area, list_area] =
-            Layout::vertical([Constraint::Length(1), Constraint::Min(3)]).areas(area);
+        // après une mise à jour, on indique ce qui a changé dans la configuration
+        let update_lines = match &self.config.update {
+            Some(update) => {
+                let mut lines = vec![Line::from(match update.from.as_str() {
+                    "" => fill(tr().updatedTo, &[&self.config.version]),
+                    from => fill(tr().updatedFrom, &[&from, &self.config.version]),
+                })
+                .yellow()];
+                for change in &update.changes {
+                    lines.push(Line::from(format!("- {}", change)).yellow());
+                }
+                lines
+            }
+            None => vec![],
+        };
+        let update_lines = match &self.configError {
+            Some(err) => [update_lines, vec![Line::from(err.clone()).red()]].concat(),
+            None => update_lines,
+        };
+        let update_lines = match &self.signalError {
+            Some(err) => [update_lines, vec![Line::from(err.clone()).red()]].concat(),
+            None => update_lines,
+        };
+        let update_lines = match &self.config.keyringError {
+            Some(err) => [
+                update_lines,
+                vec![Line::from(fill(tr().keyringNotice, &[err])).yellow()],
+            ]
+            .concat(),
+            None => update_lines,
+        };
 
-        let text = Text::from(Line::from("Que souhaitez vous faire ?"))
+        let [update_area, help_area, list_area] = Layout::vertical([
+            Constraint::Length(update_lines.len() as u16),
+            Constraint::Length(1),
+            Constraint::Min(3),
+        ])
+        .areas(area);
+        frame.render_widget(
+            Paragraph::new(Text::from(update_lines)).wrap(Wrap { trim: true }),
+            update_area,
+        );
+
+        let text = Text::from(Line::from(tr().whatToDo))
             .patch_style(Style::default().add_modifier(Modifier::RAPID_BLINK));
-        let help_message = Paragraph::new(text);
+        frame.render_widget(Paragraph::new(text), help_area);
 
         frame.render_widget(&mut self.menu, list_area);
     }
@@ -183,56 +426,138 @@ impl App {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             // .margin(1)
-            .constraints([Constraint::Min(0), Constraint::Min(0), Constraint::Min(0)])
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
             .split(area);
-        let (status_area, menu_area) = (chunks[0], chunks[1]);
+        let (status_area, help_area, menu_area) = (chunks[0], chunks[1], chunks[2]);
 
-        let last_ping = match &self.lastPingTimestamp {
+        let last_ping = match &self.connection.lastPingTimestamp {
             Some(date) => {
-                if !matches!(self.connectionStatus, ConnectionStatus::Connected)
-                    && !self.lastPingAttempt.is_none()
+                if !matches!(self.connection.status, ConnectionStatus::Connected)
+                    && self.connection.lastPingAttempt.is_some()
                 {
-                    format!(
-                        "{} (il y a {}s) (essai il y a {}s)",
-                        date.format(DATE_FORMAT).to_string(),
-                        seconds_since(self.lastPingTimestamp).unwrap_or(0),
-                        seconds_since(self.lastPingAttempt).unwrap()
+                    fill(
+                        tr().lastPingRetry,
+                        &[
+                            &date.format(DATE_FORMAT),
+                            &seconds_since(self.connection.lastPingTimestamp).unwrap_or(0),
+                            &seconds_since(self.connection.lastPingAttempt).unwrap(),
+                        ],
                     )
                 } else {
-                    format!(
-                        "{} (il y a {} secondes)",
-                        date.format(DATE_FORMAT).to_string(),
-                        seconds_since(self.lastPingTimestamp).unwrap_or(0)
+                    fill(
+                        tr().lastPingAgo,
+                        &[
+                            &date.format(DATE_FORMAT),
+                            &seconds_since(self.connection.lastPingTimestamp).unwrap_or(0),
+                        ],
                     )
                 }
             }
-            None => "N/A".to_string(),
-        };
-
-        let status = match self.connectionStatus {
-            ConnectionStatus::Uninitialized => "Non initialise".green(),
-            ConnectionStatus::Connected => "Connecté".green(),
-            ConnectionStatus::Disconnected => "Déconnecté".red(),
-            ConnectionStatus::Connecting => "Connexion en cours...".yellow(),
+            None => tr().notAvailable.to_string(),
         };
 
-        let error: String = match &self.lastError {
-            Some(error) => format!("Error: {}", error),
-            None => "".to_string(),
+        let status = match self.connection.status {
+            ConnectionStatus::Uninitialized => tr().statusUninitialized.green(),
+            ConnectionStatus::Connected => tr().statusConnected.green(),
+            ConnectionStatus::Disconnected => tr().statusDisconnected.red(),
+            ConnectionStatus::Connecting => {
+                let elapsed = self
+                    .connection
+                    .connectingSince
+                    .map(|since| since.elapsed())
+                    .unwrap_or_default();
+                let frame = (elapsed.as_millis() / FRAME_RATE as u128) as usize % SPINNER.len();
+                fill(
+                    tr().statusConnecting,
+                    &[&SPINNER[frame], &elapsed.as_secs()],
+                )
+                .yellow()
+            }
         };
 
         let mut lines = vec![
-            Line::from(format!("Statut: {}", status)),
-            Line::from(format!(
-                "Dernier login: {}",
-                self.lastLogin.as_ref().unwrap_or(&"N/A".to_string())
+            Line::from(fill(tr().statusLine, &[&status])),
+            Line::from(fill(
+                tr().lastLogin,
+                &[&self
+                    .connection
+                    .lastLogin
+                    .as_deref()
+                    .unwrap_or(tr().notAvailable)],
             )),
-            Line::from(format!("Dernier ping: {}", last_ping)),
+            Line::from(fill(tr().lastPing, &[&last_ping])),
         ];
 
-        if !error.is_empty() {
-            for line in error.lines() {
-                lines.push(Line::styled(line, Style::default().fg(Color::Red)));
+        if let Some(backend) = self.connection.backend_status() {
+            let detail = if backend.running {
+                backend.detail
+            } else {
+                fill(tr().backendNotStarted, &[&backend.detail])
+            };
+            lines.push(Line::styled(
+                fill(tr().backendLine, &[&backend.name, &detail]),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+
+        if self.connection.backoff.attempt() > 0 {
+            let retry = match (self.connection.status, self.connection.backoff.remaining()) {
+                (ConnectionStatus::Disconnected, Some(remaining)) => fill(
+                    tr().retryIn,
+                    &[
+                        &self.connection.backoff.attempt(),
+                        &(remaining.as_secs_f64().ceil() as u64),
+                    ],
+                ),
+                _ => fill(tr().retry, &[&self.connection.backoff.attempt()]),
+            };
+            lines.push(Line::styled(retry, Style::default().fg(Color::Yellow)));
+        }
+
+        // explication, conseil, puis le message technique du backend
+        if let Some(error) = &self.connection.lastError {
+            let (explanation, hint) = explain_error(error);
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                fill(tr().error, &[&explanation]),
+                Style::default().fg(Color::Red),
+            ));
+            lines.push(Line::styled(hint, Style::default().fg(Color::Yellow)));
+            if !error.message.is_empty() {
+                lines.push(Line::styled(
+                    fill(tr().errorDetail, &[&error.message]),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+        }
+
+        for error in [
+            &self.controlError,
+            &self.connection.stateError,
+            &self.connection.eventLogError,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            lines.push(Line::default());
+            lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red)));
+        }
+
+        if !self.connection.pendingLogouts.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                tr().pendingLogouts,
+                Style::default().fg(Color::Yellow),
+            ));
+            for logout in &self.connection.pendingLogouts {
+                lines.push(Line::from(fill(
+                    tr().pendingLogout,
+                    &[&logout.username, &logout.since],
+                )));
             }
         }
 
@@ -244,165 +569,277 @@ impl App {
         frame.render_widget(status_paragraph, status_area);
 
         // Render menu
-        let text = Text::from(Line::from("Que souhaitez vous faire ?"))
+        let text = Text::from(Line::from(tr().whatToDo))
             .patch_style(Style::default().add_modifier(Modifier::RAPID_BLINK));
-        let help_message = Paragraph::new(text);
+        frame.render_widget(Paragraph::new(text), help_area);
 
         frame.render_widget(&mut self.status_menu, menu_area);
     }
 
     fn refresh_status_menu(&mut self) {
-        match self.connectionStatus {
-            ConnectionStatus::Connected => {
-                self.status_menu = Menu::new("Actions", vec!["Se déconnecter".to_string()]);
-            }
-            ConnectionStatus::Disconnected => {
-                self.status_menu = Menu::new(
-                    "Actions",
-                    vec![
-                        "Essayer de se reconnecter".to_string(),
-                        "Se déconnecter".to_string(),
-                    ],
-                );
+        if self.connection.status != ConnectionStatus::Uninitialized {
+            self.status_menu
+                .set_items(status_items(self.connection.status));
+        }
+    }
+
+    fn draw_confirm_quit(&mut self, frame: &mut Frame, area: Rect) {
+        let [question_area, menu_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(3)]).areas(area);
+
+        let question = fill(
+            tr().confirmQuit,
+            &[&self.connection.username.clone().unwrap_or_default()],
+        );
+        frame.render_widget(
+            Paragraph::new(question)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            question_area,
+        );
+        frame.render_widget(&mut self.confirm_menu, menu_area);
+    }
+
+    fn draw_backend_error(&mut self, frame: &mut Frame, area: Rect) {
+        let mut lines = vec![];
+        if let Some(error) = &self.backendError {
+            let (explanation, hint) = explain_error(error);
+            lines.push(Line::from(fill(tr().error, &[&explanation])).red());
+            lines.push(Line::default());
+            lines.push(Line::from(tr().searchedLocations));
+            for location in &self.backendLocations {
+                let mark = match location.exists() {
+                    true => "✓".green(),
+                    false => "✗".red(),
+                };
+                lines.push(Line::from(vec![
+                    mark,
+                    format!(" {} ", location.path).into(),
+                    format!("({})", location.origin.label()).dark_gray(),
+                ]));
             }
-            ConnectionStatus::Connecting => {
-                self.status_menu = Menu::new("Actions", vec![]);
+            lines.push(Line::default());
+            lines.push(Line::from(hint).yellow());
+            if !error.message.is_empty() {
+                lines.push(Line::from(fill(tr().errorDetail, &[&error.message])).dark_gray());
             }
-            ConnectionStatus::Uninitialized => {}
         }
+
+        // les lignes longues se replient: le texte prend toute la place laissée par le menu
+        let [text_area, menu_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(3)]).areas(area);
+        frame.render_widget(
+            Paragraph::new(Text::from(lines)).wrap(Wrap { trim: true }),
+            text_area,
+        );
+        frame.render_widget(&mut self.backend_menu, menu_area);
     }
 
     fn draw_disconnect(&mut self, frame: &mut Frame, area: Rect) {
-        let text = Text::from(Line::from("Déconnexion en cours...").bold());
+        let text = Text::from(Line::from(tr().disconnecting).bold());
         let widget = Paragraph::new(text).alignment(Alignment::Center);
 
         frame.render_widget(widget, area);
     }
 
+    // Le texte collé va au champ en cours de saisie
+    fn handle_paste(&mut self, text: &str) {
+        match self.screen {
+            Screen::Unlock => self.passphrase_component.handle_paste(text),
+            Screen::Credentials => match self.step {
+                Step::Username => self.username_component.handle_paste(text),
+                Step::Password => self.password_component.handle_paste(text),
+            },
+            _ => {}
+        }
+    }
+
     fn handle_key_events(&mut self, key: KeyEvent) {
-        if (key.kind == KeyEventKind::Press && key.code == KeyCode::Esc) {
-            self.screen = Screen::Exit;
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+
+        // en mode brut, Ctrl-C et Ctrl-\ arrivent comme des touches et non comme SIGINT/SIGQUIT:
+        // même arrêt que pour un signal, la session est fermée avant de quitter. Selon le
+        // terminal, Ctrl-\ est lu comme Ctrl-4.
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c' | '\\' | '4'))
+        {
+            self.interrupted.store(true, Ordering::SeqCst);
+            return;
+        }
+
+        // Échap annule une connexion en cours au lieu de quitter l'écran
+        if self.screen == Screen::Status
+            && self.connection.status == ConnectionStatus::Connecting
+            && key.code == KeyCode::Esc
+        {
+            self.connection.cancel_login();
+            self.refresh_status_menu();
             return;
         }
 
         match self.screen {
+            // racine, comme l'accueil: revenir en arrière, c'est quitter
+            Screen::BackendError => match key.code {
+                KeyCode::Esc => self.back(),
+                _ => match self.backend_menu.handle_key(key) {
+                    Some(BackendAction::CheckAgain) => self.check_backend_again(),
+                    Some(BackendAction::Quit) => self.quit(),
+                    None => {}
+                },
+            },
+            Screen::Unlock => {
+                if key.code == KeyCode::Esc {
+                    self.back();
+                } else if self.passphrase_component.handle_key(key) {
+                    self.unlock();
+                }
+            }
             Screen::Home => {
-                if (key.kind == KeyEventKind::Press) {
-                    match key.code {
-                        KeyCode::Char('q') => self.screen = Screen::Exit,
-                        KeyCode::Enter => {
-                            if let Some(index) = self.menu.state.selected() {
-                                if self.config.username != "" && self.config.password != "" {
-                                    if index == 0 {
-                                        self.username = Some(self.config.username.clone());
-                                        self.password = Some(self.config.password.clone());
-                                        self.screen = Screen::Status;
-
-                                        self.login();
-                                    } else if index == 1 {
-                                        self.screen = Screen::Credentials;
-                                    } else if index == 2 {
-                                        // clear old credentials
-                                        self.config.username = "".to_string();
-                                        self.config.password = "".to_string();
-                                        self.config.save();
-
-                                        self.menu = Menu::new(
-                                            "Actions",
-                                            vec![
-                                                "Rentrer ses identifiants".to_string(),
-                                                "Quitter".to_string(),
-                                            ],
-                                        );
-                                    } else {
-                                        self.screen = Screen::Exit;
-                                    }
-                                } else {
-                                    if index == 0 {
-                                        self.screen = Screen::Credentials;
-                                    } else {
-                                        self.screen = Screen::Exit;
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            self.menu.handle_key(key);
-                        }
+                // l'accueil est la racine: revenir en arrière, c'est quitter
+                if key.code == KeyCode::Esc {
+                    self.quit();
+                    return;
+                }
+                match self.menu.handle_key(key) {
+                    Some(HomeAction::Connect) => self.connect(),
+                    Some(HomeAction::ShowStatus) => self.open(Screen::Status),
+                    Some(HomeAction::EnterCredentials) => self.open(Screen::Credentials),
+                    Some(HomeAction::ForgetCredentials) => {
+                        self.config.forget_credentials();
+                        self.save_config();
+                        self.refresh_home_menu();
                     }
+                    Some(HomeAction::Quit) => self.quit(),
+                    None => {}
                 }
             }
             Screen::Credentials => {
-                if (key.kind == KeyEventKind::Press) {
-                    let input = match self.step {
-                        Step::Username => &mut self.username_component,
-                        Step::Password => &mut self.password_component,
-                    };
+                let input = match self.step {
+                    Step::Username => &mut self.username_component,
+                    Step::Password => &mut self.password_component,
+                };
 
-                    let next = input.handle_key(key.code);
-
-                    if (next) {
-                        match self.step {
-                            Step::Username => {
-                                self.password_component.mode = InputMode::Editing;
-                                self.step = Step::Password;
-                            }
-                            Step::Password => {
-                                self.username = Some(self.username_component.value.clone());
-                                self.password = Some(self.password_component.value.clone());
-
-                                self.screen = Screen::Status;
-                                self.login();
-                            }
-                        };
+                // Échap, ou Retour arrière dans un champ vide: étape ou écran précédent
+                if key.code == KeyCode::Esc
+                    || (key.code == KeyCode::Backspace && input.value.is_empty())
+                {
+                    match self.step {
+                        Step::Username => self.back(),
+                        Step::Password => {
+                            self.password_component.mode = InputMode::Normal;
+                            self.username_component.mode = InputMode::Editing;
+                            self.step = Step::Username;
+                        }
                     }
+                    return;
                 }
-            }
-            Screen::Status => {
-                if (key.kind == KeyEventKind::Press) {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            self.screen = match self.connectionStatus {
-                                ConnectionStatus::Connected => Screen::Disconnect,
-                                _ => Screen::Exit,
-                            };
-                            self.disconnect();
-                        }
-                        KeyCode::Enter => {
-                            if let Some(index) = self.status_menu.state.selected() {
-                                if matches!(self.connectionStatus, ConnectionStatus::Connected) {
-                                    self.screen = Screen::Disconnect;
-                                    self.disconnect();
-                                } else {
-                                    if index == 0 {
-                                        // reconnect
-                                        self.reconnect();
-                                    } else {
-                                        // disconnect
-                                        self.screen = Screen::Disconnect;
-                                        self.disconnect();
-                                    }
-                                }
-                            }
+
+                let next = input.handle_key(key);
+
+                if next {
+                    match self.step {
+                        Step::Username => {
+                            self.password_component.mode = InputMode::Editing;
+                            self.step = Step::Password;
                         }
-                        _ => {
-                            self.status_menu.handle_key(key);
+                        Step::Password => {
+                            // le retour depuis le statut ramène à l'accueil, pas à la saisie
+                            self.screen = Screen::Status;
+                            self.connection.login(
+                                self.username_component.value.clone(),
+                                self.password_component.value.clone(),
+                            );
+                            self.refresh_status_menu();
                         }
-                    }
+                    };
                 }
             }
-            Screen::Disconnect => {
-                if (key.kind == KeyEventKind::Press) {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => self.screen = Screen::Exit,
-                        _ => {}
+            Screen::Status => match key.code {
+                // la session continue: l'accueil propose d'y revenir
+                KeyCode::Esc | KeyCode::Backspace => self.back(),
+                _ => match self.status_menu.handle_key(key) {
+                    Some(StatusAction::Reconnect) => {
+                        self.connection.reconnect();
+                        self.refresh_status_menu();
+                    }
+                    Some(StatusAction::Disconnect) => self.disconnect(),
+                    Some(StatusAction::Cancel) => {
+                        self.connection.cancel_login();
+                        self.refresh_status_menu();
+                    }
+                    None => {}
+                },
+            },
+            Screen::ConfirmQuit => match key.code {
+                KeyCode::Esc | KeyCode::Backspace => self.back(),
+                _ => match self.confirm_menu.handle_key(key) {
+                    Some(ConfirmAction::DisconnectAndQuit) => {
+                        self.quitting = true;
+                        self.disconnect();
                     }
+                    Some(ConfirmAction::Cancel) => self.back(),
+                    None => {}
+                },
+            },
+            Screen::Disconnect => {
+                if key.code == KeyCode::Esc {
+                    // abandonne la déconnexion en cours
+                    self.connection.abandon();
+                    self.after_disconnect();
                 }
             }
             Screen::Exit => {}
         }
     }
 
+    // Ouvre un écran; Échap y ramène à l'écran actuel
+    fn open(&mut self, screen: Screen) {
+        if screen == Screen::Credentials {
+            self.step = Step::Username;
+            self.username_component.mode = InputMode::Editing;
+            self.password_component.mode = InputMode::Normal;
+            self.password_component.submit_input();
+        }
+        let previous = std::mem::replace(&mut self.screen, screen);
+        self.history.push(previous);
+    }
+
+    // Écran précédent; depuis la racine, quitte le programme
+    fn back(&mut self) {
+        match self.history.pop() {
+            Some(previous) => {
+                self.screen = previous;
+                self.refresh_home_menu();
+            }
+            None => self.quit(),
+        }
+    }
+
+    // Quitter avec une session ouverte demande confirmation: elle sera fermée avant de quitter
+    fn quit(&mut self) {
+        if self.connection.status == ConnectionStatus::Connected {
+            self.confirm_menu = confirm_menu();
+            self.open(Screen::ConfirmQuit);
+        } else {
+            self.screen = Screen::Exit;
+        }
+    }
+
+    // Une session est ouverte, en cours d'ouverture, ou en attente de reconnexion
+    fn session_active(&self) -> bool {
+        matches!(
+            self.connection.status,
+            ConnectionStatus::Connected | ConnectionStatus::Connecting
+        ) || self.connection.backoff.attempt() > 0
+    }
+
+    fn refresh_home_menu(&mut self) {
+        self.menu
+            .set_items(home_items(&self.config, self.session_active()));
+    }
+
     fn render_header(&self, frame: &mut Frame, area: Rect) {
         let widget = BigText::builder()
             .centered()
@@ -417,10 +854,7 @@ impl App {
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
         match self.screen {
             Screen::Home => {
-                let widget = Paragraph::new(
-                    " Utilisez les flèches ↓↑ pour naviguer dans le menu, Entrée pour valider. ",
-                )
-                .centered();
+                let widget = Paragraph::new(tr().footerHome).centered();
 
                 frame.render_widget(widget, area);
             }
@@ -428,17 +862,17 @@ impl App {
                 let (msg, style) = match self.step {
                     Step::Username => (
                         vec![
-                            " Indiquez votre identifiant. Pressez ".into(),
-                            "Entrée".bold(),
-                            " pour passer à l'étape suivante ".into(),
+                            tr().footerUsername.into(),
+                            tr().enterKey.bold(),
+                            tr().footerNextStep.into(),
                         ],
                         Style::default().add_modifier(Modifier::RAPID_BLINK),
                     ),
                     Step::Password => (
                         vec![
-                            " Entrez votre mot de passe. Pressez ".into(),
-                            "Entrée".bold(),
-                            " pour passer à l'étape suivante ".into(),
+                            tr().footerPassword.into(),
+                            tr().enterKey.bold(),
+                            tr().footerNextStep.into(),
                         ],
                         Style::default(),
                     ),
@@ -450,6 +884,16 @@ impl App {
 
                 frame.render_widget(widget, area);
             }
+            Screen::Status => {
+                let widget = Paragraph::new(tr().footerStatus).centered();
+
+                frame.render_widget(widget, area);
+            }
+            Screen::BackendError => {
+                let widget = Paragraph::new(tr().footerBackend).centered();
+
+                frame.render_widget(widget, area);
+            }
             _ => {}
         }
     }
@@ -481,6 +925,12 @@ impl App {
         self.render_footer(frame, footer_area);
 
         match self.screen {
+            Screen::BackendError => {
+                self.draw_backend_error(frame, inner_screen_area);
+            }
+            Screen::Unlock => {
+                self.draw_unlock(frame, inner_screen_area);
+            }
             Screen::Home => {
                 self.draw_home(frame, inner_screen_area);
             }
@@ -490,6 +940,9 @@ impl App {
             Screen::Status => {
                 self.draw_status(frame, inner_screen_area);
             }
+            Screen::ConfirmQuit => {
+                self.draw_confirm_quit(frame, inner_screen_area);
+            }
             Screen::Disconnect => {
                 self.draw_disconnect(frame, inner_screen_area);
             }
@@ -498,32 +951,58 @@ impl App {
     }
 
     fn run(mut self, mut terminal: DefaultTerminal) -> io::Result<()> {
-        let mut last_tick = Instant::now();
-        let tick_rate = std::time::Duration::from_millis(TICK_RATE);
-
-        loop {
-            terminal.draw(|frame| {
-                let outer_block = Block::default()
-                    .title(" HXi² © | Gloire au pingouin ")
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::White));
+        let result = self.event_loop(&mut terminal);
+
+        // signal reçu, ou terminal disparu (fenêtre fermée): la session ouverte est fermée avant de
+        // quitter, avec un délai maximal
+        if result.is_err() || self.interrupted.load(Ordering::SeqCst) {
+            self.interruptHandled.store(true, Ordering::SeqCst);
+            if self.connection.disconnect() {
+                self.screen = Screen::Disconnect;
+                // terminal peut-être disparu: la déconnexion se fait quand même
+                let _ = self.draw(&mut terminal);
+                self.connection.wait(daemon::LOGOUT_TIMEOUT);
+                // sans réponse à temps, la session sera fermée au prochain lancement
+                self.connection.abandon();
+            }
+        }
+        result
+    }
 
-                // Create the layout to hold content inside the block
-                let chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([
-                        Constraint::Fill(1),
-                        Constraint::Max(80),
-                        Constraint::Fill(1),
-                    ])
-                    .split(frame.area());
+    fn draw(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        terminal.draw(|frame| {
+            let outer_block = Block::default()
+                .title(" HXi² © | Gloire au pingouin ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White));
+
+            // Create the layout to hold content inside the block
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Fill(1),
+                    Constraint::Max(80),
+                    Constraint::Fill(1),
+                ])
+                .split(frame.area());
+
+            frame.render_widget(outer_block, chunks[1]);
+
+            self.render(frame)
+        })?;
+        Ok(())
+    }
 
-                frame.render_widget(outer_block, chunks[1]);
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let mut last_tick = Instant::now();
+        let tick_rate = std::time::Duration::from_millis(TICK_RATE);
 
-                self.render(frame)
-            })?;
+        while !self.interrupted.load(Ordering::SeqCst) {
+            self.draw(terminal)?;
 
-            let timeout = tick_rate.saturating_sub(last_tick.elapsed());
+            let timeout = tick_rate
+                .saturating_sub(last_tick.elapsed())
+                .min(std::time::Duration::from_millis(FRAME_RATE));
 
             //terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
             if event::poll(timeout)? {
@@ -531,172 +1010,233 @@ impl App {
                     Event::Key(key) => {
                         self.handle_key_events(key);
                     }
+                    Event::Paste(text) => self.handle_paste(&text),
                     _ => {}
                 }
             }
 
+            if let Some(control) = &mut self.control {
+                if control.poll(&mut self.connection) {
+                    self.disconnect();
+                }
+            }
+
+            for event in self.connection.poll() {
+                self.handle_connection_event(event);
+            }
+
             if last_tick.elapsed() >= tick_rate {
                 self.on_tick();
                 last_tick = Instant::now();
             }
 
-            if (self.screen == Screen::Exit) {
-                if self.connectionStatus == ConnectionStatus::Connected {
+            if self.screen == Screen::Exit {
+                if self.connection.status == ConnectionStatus::Connected {
+                    // on passe par l'écran de déconnexion avant de quitter
+                    self.quitting = true;
                     self.disconnect();
+                    continue;
                 }
                 return Ok(());
             }
         }
+        Ok(())
     }
 
-    fn call_backend(&mut self, args: Vec<String>) -> Result<String, String> {
-        let count = args.len();
-        let input_data = format!("{}\n{}", count, args.join("\n"));
-
-        let mut child = Command::new(self.backendPath.clone())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("failed to execute child process");
+    fn disconnect(&mut self) {
+        match self.connection.disconnect() {
+            true => self.screen = Screen::Disconnect,
+            false => self.after_disconnect(),
+        }
+    }
 
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin
-                .write_all(input_data.as_bytes())
-                .expect("Failed to write to stdin");
+    // Session fermée: on quitte si c'était demandé, sinon retour à l'accueil
+    fn after_disconnect(&mut self) {
+        if self.quitting {
+            self.screen = Screen::Exit;
         } else {
-            return Err("Failed to obtain stdin".to_string());
+            self.history.clear();
+            self.screen = Screen::Home;
+            self.refresh_home_menu();
         }
+    }
+}
 
-        let timeout = std::time::Duration::from_secs(20);
-        let output_code = match child.wait_timeout(timeout).unwrap() {
-            Some(status) => status.code(),
-            None => {
-                child.kill().unwrap();
-                child.wait().unwrap().code()
-            }
-        };
-
-        let mut stdout = String::new();
-        if child.stdout.is_none() {
-            return Err("Failed to obtain stdout".to_string());
+// Les entrées restent à la même place: sans identifiants, celles qui en ont besoin sont grisées
+fn home_items(config: &Config, sessionActive: bool) -> Vec<MenuItem<HomeAction>> {
+    let saved = !config.username.is_empty() && !config.password.is_empty();
+    vec![
+        match sessionActive {
+            true => MenuItem::new(tr().showStatus, HomeAction::ShowStatus),
+            false => MenuItem::new(
+                match saved {
+                    true => fill(tr().connectAs, &[&config.username]),
+                    false => tr().connect.to_string(),
+                },
+                HomeAction::Connect,
+            )
+            .enabled(saved),
         }
-        child.stdout.unwrap().read_to_string(&mut stdout).unwrap();
+        .shortcut('c'),
+        MenuItem::new(tr().enterCredentials, HomeAction::EnterCredentials).shortcut('i'),
+        MenuItem::new(tr().forgetCredentials, HomeAction::ForgetCredentials)
+            .enabled(saved)
+            .shortcut('o'),
+        MenuItem::new(tr().quit, HomeAction::Quit).shortcut('q'),
+    ]
+}
 
-        if output_code == Some(0) {
-            Ok(stdout)
-        } else {
-            Err(stdout)
+fn status_items(status: ConnectionStatus) -> Vec<MenuItem<StatusAction>> {
+    match status {
+        ConnectionStatus::Connecting => {
+            vec![MenuItem::new(tr().cancel, StatusAction::Cancel).shortcut('a')]
+        }
+        ConnectionStatus::Disconnected => vec![
+            MenuItem::new(tr().reconnect, StatusAction::Reconnect).shortcut('r'),
+            MenuItem::new(tr().disconnect, StatusAction::Disconnect).shortcut('d'),
+        ],
+        ConnectionStatus::Connected | ConnectionStatus::Uninitialized => {
+            vec![MenuItem::new(tr().disconnect, StatusAction::Disconnect).shortcut('d')]
         }
     }
+}
 
-    fn login(&mut self) {
-        let username = self.username.as_ref().unwrap();
-        let password = self.password.as_ref().unwrap();
-
-        let args = vec!["login".to_string(), username.clone(), password.clone()];
-
-        self.connectionStatus = ConnectionStatus::Connecting;
-
-        self.lastLogin = Some(Local::now().format(DATE_FORMAT).to_string());
-
-        match self.call_backend(args) {
-            Ok(output) => {
-                self.connectionStatus = ConnectionStatus::Connected;
-                self.refresh_status_menu();
-
-                self.lastPingTimestamp = Some(Local::now());
-                self.lastPingAttempt = Some(Local::now());
-                self.lastError = None;
+fn confirm_menu() -> Menu<ConfirmAction> {
+    Menu::new(
+        tr().menuTitle,
+        vec![
+            MenuItem::new(tr().disconnectAndQuit, ConfirmAction::DisconnectAndQuit).shortcut('d'),
+            MenuItem::new(tr().cancel, ConfirmAction::Cancel).shortcut('a'),
+        ],
+    )
+}
 
-                // second line is the digest
-                self.passwordDigest = output.lines().nth(1).map(|s| s.to_string());
+fn backend_items() -> Vec<MenuItem<BackendAction>> {
+    vec![
+        MenuItem::new(tr().checkAgain, BackendAction::CheckAgain).shortcut('r'),
+        MenuItem::new(tr().quit, BackendAction::Quit).shortcut('q'),
+    ]
+}
 
-                self.config.username = self.username.clone().unwrap();
-                self.config.password = self.password.clone().unwrap();
-                self.config.save();
+fn main() {
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, tr().usage);
+            std::process::exit(2);
+        }
+    };
 
-                self.screen = Screen::Status;
-            }
-            Err(output) => {
-                self.connectionStatus = ConnectionStatus::Disconnected;
-                self.refresh_status_menu();
-                self.lastError = Some(clarify_connection_error(output));
+    // le verrou d'instance est relâché avant de quitter: `exit` ne lance pas les destructeurs
+    let code = match cli.command {
+        cli::Command::Tui => match interface(&cli) {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("{}", err);
+                1
             }
+        },
+        cli::Command::Help => {
+            print!("{}", tr().usage);
+            0
         }
-    }
-
-    fn ping(&mut self) {
-        self.lastPingAttempt = Some(Local::now());
+        cli::Command::Version => {
+            println!("ConnectionInternat {}", env!("CARGO_PKG_VERSION"));
+            0
+        }
+        cli::Command::Connect => connect(&cli),
+        _ => cli::run(&cli),
+    };
+    std::process::exit(code);
+}
 
-        let args = vec![
-            "ping".to_string(),
-            self.username.clone().unwrap_or(String::new()),
-            self.passwordDigest.clone().unwrap_or(String::new()),
-        ];
-        match self.call_backend(args) {
-            Ok(_) => {
-                self.lastPingTimestamp = Some(Local::now());
-                self.lastError = None;
-                self.connectionStatus = ConnectionStatus::Connected;
-                self.refresh_status_menu();
-            }
-            Err(output) => {
-                self.connectionStatus = ConnectionStatus::Disconnected;
-                self.refresh_status_menu();
-                self.lastError = Some(clarify_connection_error(output));
+// Mode sans interface; renvoie le code de sortie du programme
+fn connect(cli: &Cli) -> i32 {
+    let mut config = match cli.load_config() {
+        Ok(config) => config,
+        Err(err) => {
+            daemon::log(&err.to_string());
+            return 1;
+        }
+    };
+    let _lock = match instance::acquire(&config.dir(), "connect") {
+        Ok((lock, stale)) => {
+            if let Some(stale) = stale {
+                daemon::log(&instance::stale_message(&stale));
             }
+            lock
         }
+        Err(err) => return cli::attach(cli, err),
+    };
+    let (username, password) = match cli.credentials(&mut config) {
+        Ok(credentials) => credentials,
+        Err(err) => {
+            daemon::log(&err);
+            return 1;
+        }
+    };
+    // sans backend utilisable, inutile de tenter quoi que ce soit
+    let backendPath = cli.backend_path(&config);
+    if let Err(err) = backend::check(&backendPath) {
+        daemon::log(&describe_error(&err));
+        daemon::log(tr().searchedLocations);
+        for location in cli.backend_locations(&config) {
+            daemon::log(&format!(
+                "  {} ({})",
+                location.path,
+                location.origin.label()
+            ));
+        }
+        return 1;
     }
-
-    fn disconnect(&mut self) {
-        let args = vec![
-            "logout".to_string(),
-            self.username.clone().unwrap_or(String::new()),
-            self.passwordDigest.clone().unwrap_or(String::new()),
-        ];
-        self.call_backend(args);
-        self.connectionStatus = ConnectionStatus::Disconnected;
-        self.screen = Screen::Exit;
-    }
-
-    fn reconnect(&mut self) {
-        self.connectionStatus = ConnectionStatus::Connecting;
-        self.passwordDigest = None;
-
-        self.login();
+    crash::install(backendPath, config.portalUrl.clone(), config.dir(), false);
+    // les erreurs sont déjà écrites dans le journal
+    match daemon::run(
+        cli.connection(&config),
+        username,
+        password,
+        cli.socket_path(),
+    ) {
+        Ok(()) => 0,
+        Err(_) => 1,
     }
 }
 
-fn main() -> io::Result<()> {
+// Interface; renvoie le code de sortie du programme
+fn interface(cli: &Cli) -> io::Result<i32> {
+    // une configuration illisible est signalée avant d'ouvrir l'interface
+    let config = match cli.load_config() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return Ok(1);
+        }
+    };
+    // une seconde interface affiche l'état de la première au lieu d'ouvrir une autre session
+    let _lock = match instance::acquire(&config.dir(), "interface") {
+        Ok((lock, stale)) => {
+            if let Some(stale) = stale {
+                eprintln!("{}", instance::stale_message(&stale));
+            }
+            lock
+        }
+        Err(err) => return Ok(cli::attach(cli, err)),
+    };
+
+    crash::install(
+        cli.backend_path(&config),
+        config.portalUrl.clone(),
+        config.dir(),
+        true,
+    );
     let mut terminal = ratatui::init();
-    terminal.clear()?;
-    let app_result = App::new().run(terminal);
+    // un collage arrive en un seul événement, au lieu d'une touche par caractère
+    let ready = terminal
+        .clear()
+        .and_then(|()| execute!(io::stdout(), EnableBracketedPaste));
+    let app_result = ready.and_then(|()| App::new(cli, config).run(terminal));
+    // le terminal est rendu dans tous les cas, avant de signaler l'erreur
+    let pasteDisabled = execute!(io::stdout(), DisableBracketedPaste);
     ratatui::restore();
-    app_result
-}
-
-fn seconds_since(ts: Option<DateTime<Local>>) -> Option<i64> {
-    if ts.is_none() {
-        return None;
-    }
-    let now = Local::now();
-    let duration = now.signed_duration_since(ts.unwrap());
-    Some(duration.num_seconds())
-}
-
-fn clarify_connection_error(err: String) -> String {
-    let con = |description: &str| {
-        format!(
-            "{}\n\nErreur retournée par le serveur: {}",
-            description, err
-        )
-    };
-    if err.contains("404") {
-        return con(
-            "Incapable de trouver le serveur: vérifiez que vous etes connecté au bon réseau",
-        );
-    } else if err.contains("DNS") {
-        return con("Erreur de résolution DNS: vérifiez que vous etes connecté au bon réseau, et que vous n'avez pas de VPN allumé");
-    }
-    return err;
+    app_result.and(pasteDisabled).map(|()| 0)
 }
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Duration;
+
+use crate::backend::{BackendStatus, PortalBackend, Session};
+use crate::i18n::{fill, tr};
+use crate::protocol::{Action, ErrorCode, ResponseError};
+
+// Backend simulé, sans accès au contrôleur, pour tester `App` et faire des démonstrations.
+//
+// Le scénario donne, pour chaque action, la suite des résultats à renvoyer:
+//     login=ok@2;refresh=ok,ok,fail:dns;logout=ok
+// `ok` réussit, `fail:<code>` échoue avec un code d'erreur du protocole, et `@N` attend N secondes
+// avant de répondre. Une fois la suite épuisée, le dernier résultat est répété; une action absente
+// du scénario réussit toujours.
+pub struct MockBackend {
+    login: VecDeque<Outcome>,
+    refresh: VecDeque<Outcome>,
+    logout: VecDeque<Outcome>,
+    sessions: u64,
+    // erreur de lecture du scénario, renvoyée à chaque appel
+    invalid: Option<String>,
+}
+
+#[derive(Clone)]
+struct Outcome {
+    delay: Duration,
+    error: Option<ErrorCode>,
+}
+
+impl Outcome {
+    fn parse(step: &str) -> Result<Self, String> {
+        let (result, delay) = match step.split_once('@') {
+            Some((result, delay)) => {
+                let seconds = delay
+                    .parse::<u64>()
+                    .map_err(|_| fill(tr().mockInvalidDelay, &[&delay]))?;
+                (result, Duration::from_secs(seconds))
+            }
+            None => (step, Duration::ZERO),
+        };
+
+        let error = match result {
+            "ok" => None,
+            _ => match result.strip_prefix("fail:") {
+                Some(code) => Some(ErrorCode::parse(code)),
+                None => return Err(fill(tr().mockInvalidResult, &[&result])),
+            },
+        };
+
+        Ok(Self { delay, error })
+    }
+
+    fn play(&self) -> Result<(), ResponseError> {
+        thread::sleep(self.delay);
+        match self.error {
+            None => Ok(()),
+            Some(code) => Err(ResponseError::new(
+                code,
+                fill(tr().mockError, &[&code.as_str()]),
+            )),
+        }
+    }
+}
+
+impl MockBackend {
+    pub fn parse(script: &str) -> Self {
+        let mut mock = Self {
+            login: VecDeque::new(),
+            refresh: VecDeque::new(),
+            logout: VecDeque::new(),
+            sessions: 0,
+            invalid: None,
+        };
+
+        if let Err(err) = mock.load(script) {
+            mock.invalid = Some(fill(tr().mockInvalidScript, &[&err]));
+        }
+        mock
+    }
+
+    fn load(&mut self, script: &str) -> Result<(), String> {
+        for part in script.split(';').filter(|part| !part.trim().is_empty()) {
+            let (action, steps) = part
+                .split_once('=')
+                .ok_or_else(|| fill(tr().mockMissingResult, &[&part]))?;
+            let outcomes = steps
+                .split(',')
+                .map(|step| Outcome::parse(step.trim()))
+                .collect::<Result<VecDeque<_>, _>>()?;
+
+            match action.trim() {
+                "login" => self.login = outcomes,
+                "refresh" | "ping" => self.refresh = outcomes,
+                "logout" => self.logout = outcomes,
+                other => return Err(fill(tr().mockUnknownAction, &[&other])),
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self, action: Action) -> Result<(), ResponseError> {
+        if let Some(message) = &self.invalid {
+            return Err(ResponseError::new(
+                ErrorCode::InvalidRequest,
+                message.clone(),
+            ));
+        }
+
+        let queue = match action {
+            Action::Login => &mut self.login,
+            Action::Ping => &mut self.refresh,
+            Action::Logout => &mut self.logout,
+        };
+        let outcome = match queue.len() {
+            0 => return Ok(()),
+            1 => queue[0].clone(),
+            _ => queue.pop_front().unwrap(),
+        };
+        outcome.play()
+    }
+}
+
+impl PortalBackend for MockBackend {
+    fn login(&mut self, username: &str, _password: &str) -> Result<Session, ResponseError> {
+        self.next(Action::Login)?;
+        self.sessions += 1;
+        Ok(Session {
+            username: username.to_string(),
+            digest: format!("mock-digest-{}", self.sessions),
+        })
+    }
+
+    fn refresh(&mut self, _session: &Session) -> Result<(), ResponseError> {
+        self.next(Action::Ping)
+    }
+
+    fn logout(&mut self, _session: &Session) -> Result<(), ResponseError> {
+        self.next(Action::Logout)
+    }
+
+    fn status(&self) -> BackendStatus {
+        BackendStatus {
+            name: "mock",
+            running: true,
+            detail: fill(tr().mockSessions, &[&self.sessions]),
+        }
+    }
+}
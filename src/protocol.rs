@@ -0,0 +1,185 @@
+use miniserde::{json, Deserialize, Serialize};
+
+// Protocole d'échange avec le backend: une requête JSON sur une ligne dans stdin, une réponse JSON
+// sur une ligne dans stdout. Tout autre texte écrit par le backend est ignoré.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    #[serde(rename = "login")]
+    Login,
+    #[serde(rename = "ping")]
+    Ping,
+    #[serde(rename = "logout")]
+    Logout,
+}
+
+#[derive(Serialize, Default)]
+pub struct Params {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub digest: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Request {
+    pub version: u32,
+    pub id: u64,
+    pub action: Action,
+    pub params: Params,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+pub enum Status {
+    #[serde(rename = "ok")]
+    Ok,
+    #[serde(rename = "error")]
+    Error,
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct Payload {
+    pub username: Option<String>,
+    pub digest: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ResponseError {
+    pub code: String,
+    pub message: String,
+    pub http_status: Option<u16>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub version: u32,
+    pub id: u64,
+    pub status: Status,
+    pub error: Option<ResponseError>,
+    pub payload: Option<Payload>,
+}
+
+// Codes d'erreur connus; un code inconnu (backend plus récent) devient `Unknown`.
+// Chaque code a son explication et son conseil dans le catalogue des messages (`i18n`).
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ErrorCode {
+    InvalidRequest,
+    Network,
+    Dns,
+    ConnectionRefused,
+    // le contrôleur n'a pas répondu à temps
+    Timeout,
+    Tls,
+    HttpStatus,
+    InvalidCredentials,
+    MalformedResponse,
+    // le contrôleur ne reconnaît plus le digest de la session
+    SessionExpired,
+
+    // Erreurs produites côté Rust, jamais envoyées par le backend
+    BackendMissing,
+    SpawnFailed,
+    // le backend s'est arrêté, même après une relance
+    BackendUnavailable,
+    // le backend lui-même n'a pas répondu à temps
+    BackendTimeout,
+    // le backend ne parle pas la même version du protocole
+    IncompatibleBackend,
+    Cancelled,
+
+    Unknown,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::Network => "network",
+            ErrorCode::Dns => "dns",
+            ErrorCode::ConnectionRefused => "connection_refused",
+            ErrorCode::Tls => "tls",
+            ErrorCode::HttpStatus => "http_status",
+            ErrorCode::InvalidCredentials => "invalid_credentials",
+            ErrorCode::MalformedResponse => "malformed_response",
+            ErrorCode::SessionExpired => "session_expired",
+            ErrorCode::BackendMissing => "backend_missing",
+            ErrorCode::SpawnFailed => "spawn_failed",
+            ErrorCode::BackendUnavailable => "backend_unavailable",
+            ErrorCode::BackendTimeout => "backend_timeout",
+            ErrorCode::IncompatibleBackend => "incompatible_backend",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(code: &str) -> ErrorCode {
+        match code {
+            "invalid_request" => ErrorCode::InvalidRequest,
+            "network" => ErrorCode::Network,
+            "dns" => ErrorCode::Dns,
+            "connection_refused" => ErrorCode::ConnectionRefused,
+            "tls" => ErrorCode::Tls,
+            "http_status" => ErrorCode::HttpStatus,
+            "invalid_credentials" => ErrorCode::InvalidCredentials,
+            "malformed_response" => ErrorCode::MalformedResponse,
+            "session_expired" => ErrorCode::SessionExpired,
+            "backend_missing" => ErrorCode::BackendMissing,
+            "spawn_failed" => ErrorCode::SpawnFailed,
+            "backend_unavailable" => ErrorCode::BackendUnavailable,
+            "backend_timeout" => ErrorCode::BackendTimeout,
+            "incompatible_backend" => ErrorCode::IncompatibleBackend,
+            "timeout" => ErrorCode::Timeout,
+            "cancelled" => ErrorCode::Cancelled,
+            _ => ErrorCode::Unknown,
+        }
+    }
+}
+
+impl ResponseError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code: code.as_str().to_string(),
+            message: message.into(),
+            http_status: None,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        ErrorCode::parse(&self.code)
+    }
+}
+
+impl Request {
+    pub fn new(id: u64, action: Action, params: Params) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            id,
+            action,
+            params,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}\n", json::to_string(self))
+    }
+}
+
+pub fn response_result(response: Response) -> Result<Payload, ResponseError> {
+    if response.version != PROTOCOL_VERSION {
+        return Err(ResponseError::new(
+            ErrorCode::IncompatibleBackend,
+            format!(
+                "Version du protocole incompatible: {} (attendue: {})",
+                response.version, PROTOCOL_VERSION
+            ),
+        ));
+    }
+
+    match response.status {
+        Status::Ok => Ok(response.payload.unwrap_or_default()),
+        Status::Error => Err(response.error.unwrap_or_else(|| {
+            ResponseError::new(ErrorCode::MalformedResponse, "Erreur sans description")
+        })),
+    }
+}
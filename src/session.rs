@@ -0,0 +1,97 @@
+use miniserde::{json, Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::restrict_permissions;
+
+const SESSION_FILE_NAME: &str = "session.json";
+const PENDING_FILE_NAME: &str = "pending-logouts.json";
+
+// Session ouverte sur le contrôleur, gardée d'un lancement à l'autre: au démarrage suivant, un ping
+// avec ce digest suffit à la reprendre, sans nouvelle connexion. Le digest donne accès à la session:
+// les fichiers ne sont lisibles que par leur propriétaire, comme la configuration.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedSession {
+    pub username: String,
+    pub digest: String,
+    pub lastLogin: String,
+}
+
+// Session dont la déconnexion a échoué (réseau déjà coupé, programme arrêté avant la réponse): elle
+// reste ouverte sur le contrôleur, et sa déconnexion est retentée au lancement suivant ou dès que le
+// contrôleur répond à nouveau
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingLogout {
+    pub username: String,
+    pub digest: String,
+    // première tentative de déconnexion
+    pub since: String,
+}
+
+pub fn path(configDir: &Path) -> PathBuf {
+    configDir.join(SESSION_FILE_NAME)
+}
+
+pub fn pending_path(configDir: &Path) -> PathBuf {
+    configDir.join(PENDING_FILE_NAME)
+}
+
+// Un fichier absent ou illisible revient à ne pas avoir de session
+pub fn load(path: &Path) -> Option<SavedSession> {
+    let content = fs::read_to_string(path).ok()?;
+    json::from_str(content.trim()).ok()
+}
+
+pub fn save(path: &Path, session: &SavedSession) -> io::Result<()> {
+    write_private(path, &json::to_string(session))
+}
+
+// Un fichier déjà absent n'est pas une erreur
+pub fn clear(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+pub fn load_pending(path: &Path) -> Vec<PendingLogout> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| json::from_str(content.trim()).ok())
+        .unwrap_or_default()
+}
+
+// Une liste vide supprime le fichier
+pub fn save_pending(path: &Path, pending: &[PendingLogout]) -> io::Result<()> {
+    if pending.is_empty() {
+        return clear(path);
+    }
+    write_private(path, &json::to_string(&pending.to_vec()))
+}
+
+// Ajoute une session à fermer plus tard, sans doublon
+pub fn defer_logout(path: &Path, logout: PendingLogout) -> io::Result<()> {
+    let mut pending = load_pending(path);
+    if !pending.iter().any(|other| other.digest == logout.digest) {
+        pending.push(logout);
+    }
+    save_pending(path, &pending)
+}
+
+fn write_private(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // jamais lisible par les autres, même un instant
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(format!("{}\n", content).as_bytes())?;
+    restrict_permissions(path)
+}
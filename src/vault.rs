@@ -0,0 +1,92 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fmt;
+
+use crate::i18n::tr;
+
+// Coffre: le mot de passe du portail est chiffré avec une clé tirée d'une phrase de passe.
+// Format stocké dans la configuration: `v1:<sel>:<nonce>:<chiffré>`, en hexadécimal. La version
+// fixe les algorithmes (Argon2id avec ses paramètres par défaut, puis ChaCha20-Poly1305).
+const FORMAT: &str = "v1";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+pub enum VaultError {
+    Malformed,
+    WrongPassphrase,
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VaultError::Malformed => write!(f, "{}", tr().vaultMalformed),
+            VaultError::WrongPassphrase => write!(f, "{}", tr().vaultWrongPassphrase),
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .expect("paramètres Argon2 invalides");
+    key
+}
+
+// Un nouveau sel et un nouveau nonce à chaque appel: deux sauvegardes ne donnent jamais le même texte
+pub fn seal(secret: &str, passphrase: &str) -> String {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt));
+    let sealed = cipher
+        .encrypt(&nonce, secret.as_bytes())
+        .expect("chiffrement impossible");
+
+    format!(
+        "{}:{}:{}:{}",
+        FORMAT,
+        to_hex(&salt),
+        to_hex(&nonce),
+        to_hex(&sealed)
+    )
+}
+
+pub fn open(sealed: &str, passphrase: &str) -> Result<String, VaultError> {
+    let parts: Vec<&str> = sealed.split(':').collect();
+    let (salt, nonce, data) = match parts.as_slice() {
+        [FORMAT, salt, nonce, data] => (
+            from_hex(salt).ok_or(VaultError::Malformed)?,
+            from_hex(nonce).ok_or(VaultError::Malformed)?,
+            from_hex(data).ok_or(VaultError::Malformed)?,
+        ),
+        _ => return Err(VaultError::Malformed),
+    };
+    if salt.len() != SALT_LENGTH || nonce.len() != NONCE_LENGTH {
+        return Err(VaultError::Malformed);
+    }
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt));
+    // l'authentification échoue aussi bien pour une mauvaise phrase que pour un texte modifié
+    let secret = cipher
+        .decrypt(Nonce::from_slice(&nonce), data.as_slice())
+        .map_err(|_| VaultError::WrongPassphrase)?;
+    String::from_utf8(secret).map_err(|_| VaultError::Malformed)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
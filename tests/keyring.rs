@@ -0,0 +1,182 @@
+// Trousseau de bout en bout: un bus de session privé, le service de remplacement
+// (support/fake_secret_service.rs, servi depuis l'essai lui-même), puis les commandes du programme.
+// Sans `dbus-daemon`, l'essai avec le service est sauté.
+#![cfg(target_os = "linux")]
+
+mod support;
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+const PROGRAM: &str = env!("CARGO_BIN_EXE_ConnectionInternat");
+// adresse sans bus derrière: machine sans session de bureau
+const NO_BUS: &str = "unix:path=/nonexistent/connection-internat-bus";
+
+struct Bus {
+    address: String,
+    daemon: Child,
+    // le service s'arrête avec sa connexion
+    _service: zbus::blocking::Connection,
+}
+
+impl Bus {
+    fn start() -> Option<Self> {
+        let mut daemon = match Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(daemon) => daemon,
+            Err(err) => {
+                eprintln!("dbus-daemon indisponible ({}), essai sauté", err);
+                return None;
+            }
+        };
+        let address = first_line(&mut daemon);
+
+        // le nom est pris sur le bus avant le retour: le service est prêt
+        let service = support::fake_secret_service::serve(&address).unwrap();
+        Some(Self {
+            address,
+            daemon,
+            _service: service,
+        })
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        // processus déjà arrêté: rien à faire de plus
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+// La suite de la sortie est lue jusqu'au bout: sans lecteur, le bus s'arrêterait à son prochain
+// message
+fn first_line(child: &mut Child) -> String {
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    thread::spawn(move || io::copy(&mut reader, &mut io::sink()));
+    line.trim().to_string()
+}
+
+// Dossier de configuration propre à l'essai
+fn config(name: &str, settings: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "connection-internat-keyring-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    fs::write(
+        &path,
+        format!("schema = 3\nlanguage = \"fr\"\n{}", settings),
+    )
+    .unwrap();
+    path
+}
+
+// Sortie et erreurs de la commande, qui doit réussir
+fn run(config: &Path, bus: &str, args: &[&str], input: &str) -> String {
+    let mut child = Command::new(PROGRAM)
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .env("HOME", config.parent().unwrap())
+        .env("DBUS_SESSION_BUS_ADDRESS", bus)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.status.success(), "{:?} a échoué:\n{}", args, text);
+    text
+}
+
+#[test]
+fn password_is_stored_in_the_keyring() {
+    let bus = match Bus::start() {
+        Some(bus) => bus,
+        None => return,
+    };
+    let path = config("stored", "");
+
+    let output = run(
+        &path,
+        &bus.address,
+        &["set-credentials", "--username", "alice"],
+        "secret\n",
+    );
+    assert!(output.contains("trousseau"), "{}", output);
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(
+        content.contains("password_storage = \"keyring\""),
+        "{}",
+        content
+    );
+    assert!(!content.contains("secret"), "{}", content);
+
+    // relu depuis le service au lancement suivant
+    let output = run(&path, &bus.address, &["status"], "");
+    assert!(
+        output.contains("mot de passe dans le trousseau"),
+        "{}",
+        output
+    );
+
+    run(&path, &bus.address, &["forget-credentials"], "");
+    let output = run(&path, &bus.address, &["status"], "");
+    assert!(
+        output.contains("Identifiant sauvegardé: aucun"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn missing_keyring_is_quiet_by_default() {
+    let path = config("quiet", "");
+    let output = run(
+        &path,
+        NO_BUS,
+        &["set-credentials", "--username", "alice"],
+        "secret\n",
+    );
+    assert!(!output.contains("Trousseau"), "{}", output);
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("password = \"secret\""), "{}", content);
+}
+
+#[test]
+fn missing_keyring_is_reported_when_requested() {
+    let path = config("requested", "use_keyring = true\n");
+    let output = run(
+        &path,
+        NO_BUS,
+        &["set-credentials", "--username", "alice"],
+        "secret\n",
+    );
+    assert!(output.contains("Trousseau"), "{}", output);
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("password = \"secret\""), "{}", content);
+}
@@ -0,0 +1,346 @@
+// Service Secret Service de remplacement, pour essayer le trousseau sans session de bureau. Il garde
+// les entrées en mémoire et n'accepte que les sessions chiffrées (échange de clés Diffie-Hellman,
+// puis AES-128-CBC), comme le programme les demande.
+//
+// Une seule collection, `login`, alias `default`, jamais verrouillée.
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hkdf::Hkdf;
+use num_bigint::BigUint;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{fdo, interface, ObjectServer};
+
+const ROOT: &str = "/org/freedesktop/secrets";
+const COLLECTION: &str = "/org/freedesktop/secrets/collection/login";
+const SESSIONS: &str = "/org/freedesktop/secrets/session";
+const ALGORITHM: &str = "dh-ietf1024-sha256-aes128-cbc-pkcs7";
+
+// Groupe 2 de la RFC 2409 (1024 bits), générateur 2
+const PRIME: &str = "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7EDEE386BFB5A899FA5AE9F24117C4B1FE649286651ECE65381FFFFFFFFFFFFFFFF";
+const KEY_LENGTH: usize = 128;
+
+// session, paramètres (vecteur d'initialisation), valeur chiffrée, type de contenu
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+type Key = [u8; 16];
+
+struct Entry {
+    label: String,
+    attributes: HashMap<String, String>,
+    secret: Vec<u8>,
+    content_type: String,
+    deleted: bool,
+}
+
+// Entrées (en clair) et clés des sessions ouvertes
+#[derive(Clone, Default)]
+struct Store {
+    entries: Arc<Mutex<Vec<Entry>>>,
+    sessions: Arc<Mutex<Vec<Key>>>,
+}
+
+impl Store {
+    fn search(&self, attributes: &HashMap<String, String>) -> Vec<OwnedObjectPath> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                !entry.deleted
+                    && attributes
+                        .iter()
+                        .all(|(key, value)| entry.attributes.get(key) == Some(value))
+            })
+            .map(|(index, _)| item_path(index))
+            .collect()
+    }
+
+    fn items(&self) -> Vec<OwnedObjectPath> {
+        self.search(&HashMap::new())
+    }
+
+    fn key(&self, session: &OwnedObjectPath) -> fdo::Result<Key> {
+        let index = index(session)
+            .filter(|_| session.as_str().starts_with(SESSIONS))
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("session inconnue: {}", session)))?;
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(index)
+            .copied()
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("session inconnue: {}", session)))
+    }
+
+    fn decrypt(&self, secret: &Secret) -> fdo::Result<Vec<u8>> {
+        let (session, iv, value, _) = secret;
+        let key = self.key(session)?;
+        let decryptor = cbc::Decryptor::<aes::Aes128>::new_from_slices(&key, iv)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        decryptor
+            .decrypt_padded_vec_mut::<Pkcs7>(value)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))
+    }
+
+    fn encrypt(&self, session: OwnedObjectPath, entry: &Entry) -> fdo::Result<Secret> {
+        let key = self.key(&session)?;
+        let iv: [u8; 16] = rand::random();
+        let value = cbc::Encryptor::<aes::Aes128>::new(&key.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(&entry.secret);
+        Ok((session, iv.to_vec(), value, entry.content_type.clone()))
+    }
+}
+
+fn path(path: &str) -> OwnedObjectPath {
+    ObjectPath::try_from(path).unwrap().into()
+}
+
+fn item_path(index: usize) -> OwnedObjectPath {
+    path(&format!("{}/{}", COLLECTION, index))
+}
+
+fn index(object: &OwnedObjectPath) -> Option<usize> {
+    object.as_str().rsplit('/').next()?.parse().ok()
+}
+
+fn no_prompt() -> OwnedObjectPath {
+    path("/")
+}
+
+// Clé de session partagée avec le client, d'après sa clé publique; renvoie aussi la nôtre
+fn exchange_keys(client: &[u8]) -> (Vec<u8>, Key) {
+    let prime = BigUint::parse_bytes(PRIME.as_bytes(), 16).unwrap();
+    let private = BigUint::from_bytes_be(&rand::random::<[u8; 32]>());
+    let public = BigUint::from(2u32).modpow(&private, &prime);
+    let shared = BigUint::from_bytes_be(client)
+        .modpow(&private, &prime)
+        .to_bytes_be();
+
+    // le secret commun est complété à gauche jusqu'à la taille du groupe
+    let mut padded = vec![0; KEY_LENGTH - shared.len()];
+    padded.extend(shared);
+    let mut key = [0; 16];
+    Hkdf::<Sha256>::new(None, &padded)
+        .expand(&[], &mut key)
+        .unwrap();
+    (public.to_bytes_be(), key)
+}
+
+struct Service {
+    store: Store,
+}
+
+#[interface(name = "org.freedesktop.Secret.Service")]
+impl Service {
+    fn open_session(
+        &self,
+        algorithm: &str,
+        input: Value<'_>,
+    ) -> fdo::Result<(OwnedValue, OwnedObjectPath)> {
+        if algorithm != ALGORITHM {
+            return Err(fdo::Error::NotSupported(algorithm.to_string()));
+        }
+        let client =
+            Vec::<u8>::try_from(input).map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        let (public, key) = exchange_keys(&client);
+
+        let mut sessions = self.store.sessions.lock().unwrap();
+        sessions.push(key);
+        let session = path(&format!("{}/{}", SESSIONS, sessions.len() - 1));
+        let output = OwnedValue::try_from(Value::from(public)).unwrap();
+        Ok((output, session))
+    }
+
+    fn search_items(
+        &self,
+        attributes: HashMap<String, String>,
+    ) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+        (self.store.search(&attributes), Vec::new())
+    }
+
+    fn unlock(&self, objects: Vec<OwnedObjectPath>) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+        (objects, no_prompt())
+    }
+
+    fn read_alias(&self, name: &str) -> OwnedObjectPath {
+        match name {
+            "default" | "login" => path(COLLECTION),
+            _ => no_prompt(),
+        }
+    }
+
+    #[zbus(property)]
+    fn collections(&self) -> Vec<OwnedObjectPath> {
+        vec![path(COLLECTION)]
+    }
+}
+
+struct Collection {
+    store: Store,
+}
+
+#[interface(name = "org.freedesktop.Secret.Collection")]
+impl Collection {
+    fn search_items(&self, attributes: HashMap<String, String>) -> Vec<OwnedObjectPath> {
+        self.store.search(&attributes)
+    }
+
+    async fn create_item(
+        &self,
+        properties: HashMap<String, OwnedValue>,
+        secret: Secret,
+        replace: bool,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+        let property = |name: &str| {
+            properties
+                .get(name)
+                .and_then(|value| value.try_clone().ok())
+                .ok_or_else(|| fdo::Error::InvalidArgs(format!("{} manquant", name)))
+        };
+        let label = String::try_from(property("org.freedesktop.Secret.Item.Label")?)
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        let attributes = HashMap::<String, String>::try_from(property(
+            "org.freedesktop.Secret.Item.Attributes",
+        )?)
+        .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        let value = self.store.decrypt(&secret)?;
+        let (_, _, _, content_type) = secret;
+
+        let existing = match replace {
+            true => self.store.search(&attributes).into_iter().next(),
+            false => None,
+        };
+        if let Some(item) = existing {
+            let index = index(&item).unwrap();
+            let mut entries = self.store.entries.lock().unwrap();
+            let entry = &mut entries[index];
+            entry.label = label;
+            entry.secret = value;
+            entry.content_type = content_type;
+            return Ok((item, no_prompt()));
+        }
+
+        let index = {
+            let mut entries = self.store.entries.lock().unwrap();
+            entries.push(Entry {
+                label,
+                attributes,
+                secret: value,
+                content_type,
+                deleted: false,
+            });
+            entries.len() - 1
+        };
+        let item = item_path(index);
+        let object = Item {
+            store: self.store.clone(),
+            index,
+        };
+        server.at(&item, object).await?;
+        Ok((item, no_prompt()))
+    }
+
+    #[zbus(property)]
+    fn items(&self) -> Vec<OwnedObjectPath> {
+        self.store.items()
+    }
+
+    #[zbus(property)]
+    fn label(&self) -> String {
+        "login".to_string()
+    }
+
+    #[zbus(property)]
+    fn locked(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn created(&self) -> u64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn modified(&self) -> u64 {
+        0
+    }
+}
+
+// Une entrée supprimée garde son objet sur le bus, mais n'est plus trouvée ni lisible
+struct Item {
+    store: Store,
+    index: usize,
+}
+
+impl Item {
+    fn with<T>(&self, read: impl FnOnce(&mut Entry) -> T) -> fdo::Result<T> {
+        let mut entries = self.store.entries.lock().unwrap();
+        match entries.get_mut(self.index) {
+            Some(entry) if !entry.deleted => Ok(read(entry)),
+            _ => Err(fdo::Error::UnknownObject(item_path(self.index).to_string())),
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.Secret.Item")]
+impl Item {
+    fn delete(&self) -> fdo::Result<OwnedObjectPath> {
+        self.with(|entry| entry.deleted = true)?;
+        Ok(no_prompt())
+    }
+
+    fn get_secret(&self, session: OwnedObjectPath) -> fdo::Result<Secret> {
+        self.with(|entry| self.store.encrypt(session, entry))?
+    }
+
+    fn set_secret(&self, secret: Secret) -> fdo::Result<()> {
+        let value = self.store.decrypt(&secret)?;
+        let (_, _, _, content_type) = secret;
+        self.with(|entry| {
+            entry.secret = value;
+            entry.content_type = content_type;
+        })
+    }
+
+    #[zbus(property)]
+    fn locked(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn attributes(&self) -> fdo::Result<HashMap<String, String>> {
+        self.with(|entry| entry.attributes.clone())
+    }
+
+    #[zbus(property)]
+    fn label(&self) -> fdo::Result<String> {
+        self.with(|entry| entry.label.clone())
+    }
+
+    #[zbus(property)]
+    fn created(&self) -> u64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn modified(&self) -> u64 {
+        0
+    }
+}
+
+// Sert le trousseau sur le bus à cette adresse, tant que la connexion renvoyée reste ouverte
+pub fn serve(address: &str) -> zbus::Result<zbus::blocking::Connection> {
+    let store = Store::default();
+    zbus::blocking::connection::Builder::address(address)?
+        .name("org.freedesktop.secrets")?
+        .serve_at(
+            ROOT,
+            Service {
+                store: store.clone(),
+            },
+        )?
+        .serve_at(COLLECTION, Collection { store })?
+        .build()
+}